@@ -0,0 +1,79 @@
+use crate::fft::{Complex, Fft};
+use crate::filter::Filter;
+use crate::reverb::Reverb;
+use crate::sample::Sample;
+
+/// Resets `filter` and renders its response to a unit impulse
+pub fn impulse_response<S: Sample, F: Filter<S> + ?Sized>(filter: &mut F, len: usize) -> Vec<S> {
+    filter.reset();
+    (0..len)
+        .map(|n| filter.process_sample(S::from_f64(if n == 0 { 1.0 } else { 0.0 })))
+        .collect()
+}
+
+/// Resets `reverb` and renders its response to a unit impulse fed to both channels
+pub fn reverb_impulse_response<S: Sample, R: Reverb<S> + ?Sized>(
+    reverb: &mut R,
+    len: usize,
+) -> (Vec<S>, Vec<S>) {
+    reverb.reset();
+    let mut input = vec![S::ZERO; len];
+    if let Some(x) = input.first_mut() {
+        *x = S::from_f64(1.0);
+    }
+    let mut output = (vec![S::ZERO; len], vec![S::ZERO; len]);
+    reverb.process_block((&input, &input), (&mut output.0, &mut output.1));
+    output
+}
+
+pub struct FrequencyResponse {
+    /// Frequencies of the bins in Hz, from DC to Nyquist
+    pub frequencies: Vec<f64>,
+    pub magnitude: Vec<f64>,
+    /// Wrapped phase in radians
+    pub phase: Vec<f64>,
+    /// Group delay in samples
+    pub group_delay: Vec<f64>,
+}
+
+impl FrequencyResponse {
+    /// Computes the response from an impulse response zero-padded to a power of two
+    pub fn from_impulse_response<S: Sample>(impulse_response: &[S], sample_rate: u32) -> Self {
+        let size = impulse_response.len().max(2).next_power_of_two();
+        let fft = Fft::new(size);
+
+        // https://ccrma.stanford.edu/~jos/fp/Numerical_Computation_Group_Delay.html
+        let mut h = vec![Complex::default(); size];
+        let mut nh = vec![Complex::default(); size];
+        for (n, x) in impulse_response.iter().enumerate() {
+            h[n].re = x.to_f64();
+            nh[n].re = n as f64 * x.to_f64();
+        }
+        fft.forward(&mut h);
+        fft.forward(&mut nh);
+
+        let bins = 0..=size / 2;
+        Self {
+            frequencies: bins
+                .clone()
+                .map(|k| k as f64 * f64::from(sample_rate) / size as f64)
+                .collect(),
+            magnitude: h[bins.clone()].iter().map(|x| x.norm()).collect(),
+            phase: h[bins.clone()].iter().map(|x| x.arg()).collect(),
+            group_delay: bins
+                .map(|k| {
+                    let denom = h[k].norm_sqr();
+                    if denom > 0.0 {
+                        (nh[k] * h[k].conj()).re / denom
+                    } else {
+                        0.0
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn magnitude_db(&self) -> Vec<f64> {
+        self.magnitude.iter().map(|m| 20.0 * m.log10()).collect()
+    }
+}
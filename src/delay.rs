@@ -1,30 +1,417 @@
-pub struct Delay {
-    buffer: Vec<f64>,
-    read_ptr: usize,
+use crate::lfo::Lfo;
+use crate::sample::Sample;
+
+// https://ccrma.stanford.edu/~jos/pasp/Delay_Line_Interpolation.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Rounds the length to the nearest integer
+    None,
+    Linear,
+    /// Third-order Lagrange interpolation
+    Lagrange,
+    /// First-order allpass interpolation, not suitable for modulated lengths
+    Allpass,
+    /// Thiran allpass interpolation of the given order, which has maximally flat group delay
+    /// and no high frequency loss. Not suitable for fast modulation of the length
+    Thiran(usize),
+}
+
+/// How the length of a `Delay` moves to a new value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transition {
+    /// Jumps to the new length, which clicks unless the delay line is silent
+    Immediate,
+    /// Moves the length linearly over the given number of samples, which bends the pitch
+    Glide(usize),
+    /// Crossfades between the old and the new length over the given number of samples
+    Crossfade(usize),
+}
+
+pub trait DelayLine<S: Sample = f64> {
+    fn input(&mut self, x: S);
+    fn output(&self) -> S;
+    fn reset(&mut self);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tap {
+    /// Delay in samples, which can be longer than the length of the delay
+    /// as long as it fits in the allocated buffer
+    pub position: f64,
+    pub gain: f64,
+}
+
+pub struct Delay<S = f64> {
+    buffer: Vec<S>,
     write_ptr: usize,
+    length: f64,
+    interpolation: Interpolation,
+    allpass_output: S,
+    thiran: Thiran<S>,
+    transition: TransitionState,
+}
+
+enum TransitionState {
+    None,
+    Glide {
+        target: f64,
+        increment: f64,
+        remaining: usize,
+    },
+    Crossfade {
+        from: f64,
+        position: usize,
+        duration: usize,
+    },
 }
 
-impl Delay {
+impl<S: Sample> Delay<S> {
     pub fn new(length: usize) -> Self {
-        let buf_len = length.next_power_of_two();
+        Self::fractional(length as f64, Interpolation::None)
+    }
+
+    pub fn fractional(length: f64, interpolation: Interpolation) -> Self {
+        Self::with_max_length(length, length, interpolation)
+    }
+
+    /// Allocates a buffer so that the length can later be changed up to `max_length`
+    pub fn with_max_length(length: f64, max_length: f64, interpolation: Interpolation) -> Self {
+        let length = length.max(min_length(interpolation));
+        let max_length = max_length.max(length);
         Self {
-            buffer: vec![0.0; buf_len],
-            read_ptr: 0,
-            write_ptr: if length == buf_len {
-                0 // wrap around
-            } else {
-                length
-            },
+            buffer: vec![
+                S::ZERO;
+                (max_length as usize + 3 + thiran_order(interpolation)).next_power_of_two()
+            ],
+            write_ptr: 0,
+            length,
+            interpolation,
+            allpass_output: S::ZERO,
+            thiran: Thiran::new(interpolation, length),
+            transition: TransitionState::None,
+        }
+    }
+
+    pub fn from_millis(sample_rate: u32, ms: f64, interpolation: Interpolation) -> Self {
+        Self::fractional(f64::from(sample_rate) * ms / 1000.0, interpolation)
+    }
+
+    pub fn length(&self) -> f64 {
+        self.length
+    }
+
+    /// Longest length that fits in the allocated buffer, which is at least the
+    /// `max_length` given on construction
+    pub fn max_length(&self) -> f64 {
+        (self.buffer.len() - 3 - thiran_order(self.interpolation)) as f64
+    }
+
+    /// Changes the length, which is clamped to `max_length()`.
+    /// A transition in progress is cut short and the new one starts from the current length
+    pub fn set_length(&mut self, length: f64, transition: Transition) {
+        let length = length
+            .max(min_length(self.interpolation))
+            .min(self.max_length());
+        match transition {
+            Transition::Glide(duration) if duration > 0 => {
+                self.transition = TransitionState::Glide {
+                    target: length,
+                    increment: (length - self.length) / duration as f64,
+                    remaining: duration,
+                };
+            }
+            Transition::Crossfade(duration) if duration > 0 => {
+                self.transition = TransitionState::Crossfade {
+                    from: self.length,
+                    position: 0,
+                    duration,
+                };
+                self.update_length(length);
+            }
+            _ => {
+                self.transition = TransitionState::None;
+                self.update_length(length);
+            }
+        }
+    }
+
+    pub fn tap_output(&self, position: f64) -> S {
+        let interpolation = stateless(self.interpolation);
+        let max_length = (self.buffer.len() - 3) as f64;
+        let position = position.max(min_length(interpolation)).min(max_length);
+        self.read(position, interpolation)
+    }
+
+    pub fn taps_output(&self, taps: &[Tap]) -> S {
+        taps.iter()
+            .map(|tap| S::from_f64(tap.gain) * self.tap_output(tap.position))
+            .sum()
+    }
+
+    fn read(&self, length: f64, interpolation: Interpolation) -> S {
+        let c = S::from_f64;
+        match interpolation {
+            Interpolation::None => self.past(length.round() as usize),
+            Interpolation::Linear => {
+                let i = length.floor();
+                let frac = length - i;
+                let i = i as usize;
+                c(1.0 - frac) * self.past(i) + c(frac) * self.past(i + 1)
+            }
+            Interpolation::Lagrange => {
+                // taps are chosen so that the fractional delay is in [1, 2),
+                // where the error of 3rd-order Lagrange interpolation is minimal
+                let i = length.floor() - 1.0;
+                let d = length - i;
+                let i = i as usize;
+                let (dm1, dm2, dm3) = (d - 1.0, d - 2.0, d - 3.0);
+                c(-dm1 * dm2 * dm3 / 6.0) * self.past(i)
+                    + c(d * dm2 * dm3 / 2.0) * self.past(i + 1)
+                    + c(-d * dm1 * dm3 / 2.0) * self.past(i + 2)
+                    + c(d * dm1 * dm2 / 6.0) * self.past(i + 3)
+            }
+            Interpolation::Allpass => {
+                // fractional delay is kept in [0.5, 1.5) for stability of the coefficient
+                let i = (length - 0.5).floor();
+                let frac = length - i;
+                let eta = c((1.0 - frac) / (1.0 + frac));
+                let i = i as usize;
+                eta * self.past(i) + self.past(i + 1) - eta * self.allpass_output
+            }
+            Interpolation::Thiran(order) => {
+                // coefficients are precomputed for `self.length`
+                let a = &self.thiran.coefficients;
+                let feedforward: S = (0..=order)
+                    .map(|k| a[order - k] * self.past(self.thiran.integer + k))
+                    .sum();
+                let feedback: S = (1..=order).map(|k| a[k] * self.thiran.outputs[k - 1]).sum();
+                feedforward - feedback
+            }
+        }
+    }
+
+    fn advance_transition(&mut self) {
+        match self.transition {
+            TransitionState::None => (),
+            TransitionState::Glide {
+                target,
+                increment,
+                remaining,
+            } => {
+                if remaining > 1 {
+                    self.update_length(self.length + increment);
+                    self.transition = TransitionState::Glide {
+                        target,
+                        increment,
+                        remaining: remaining - 1,
+                    };
+                } else {
+                    self.update_length(target);
+                    self.transition = TransitionState::None;
+                }
+            }
+            TransitionState::Crossfade {
+                from,
+                position,
+                duration,
+            } => {
+                self.transition = if position + 1 < duration {
+                    TransitionState::Crossfade {
+                        from,
+                        position: position + 1,
+                        duration,
+                    }
+                } else {
+                    TransitionState::None
+                };
+            }
+        }
+    }
+
+    fn update_length(&mut self, length: f64) {
+        self.length = length;
+        if let Interpolation::Thiran(_) = self.interpolation {
+            self.thiran.set_length(length);
         }
     }
 
-    pub fn input(&mut self, x: f64) {
-        self.buffer[self.write_ptr] = x;
-        self.read_ptr = (self.read_ptr + 1) & (self.buffer.len() - 1);
+    // returns the sample inputted `n` samples ago
+    fn past(&self, n: usize) -> S {
+        self.buffer[self.write_ptr.wrapping_sub(n) & (self.buffer.len() - 1)]
+    }
+}
+
+impl<S: Sample> DelayLine<S> for Delay<S> {
+    fn input(&mut self, x: S) {
+        // the allpass states follow the output at the current length even while crossfading
+        match self.interpolation {
+            Interpolation::Allpass => {
+                self.allpass_output = self.read(self.length, self.interpolation).flush_denormal()
+            }
+            Interpolation::Thiran(order) if order > 0 => {
+                let output = self.read(self.length, self.interpolation).flush_denormal();
+                self.thiran.outputs.rotate_right(1);
+                self.thiran.outputs[0] = output;
+            }
+            _ => (),
+        }
+        self.buffer[self.write_ptr] = x.flush_denormal();
         self.write_ptr = (self.write_ptr + 1) & (self.buffer.len() - 1);
+        self.advance_transition();
+    }
+
+    fn output(&self) -> S {
+        let output = self.read(self.length, self.interpolation);
+        match self.transition {
+            TransitionState::Crossfade {
+                from,
+                position,
+                duration,
+            } => {
+                let t = S::from_f64(position as f64 / duration as f64);
+                let interpolation = stateless(self.interpolation);
+                let old = self.read(from.max(min_length(interpolation)), interpolation);
+                old + t * (output - old)
+            }
+            _ => output,
+        }
+    }
+
+    fn reset(&mut self) {
+        if let TransitionState::Glide { target, .. } = self.transition {
+            self.update_length(target);
+        }
+        self.transition = TransitionState::None;
+        self.buffer.iter_mut().for_each(|x| *x = S::ZERO);
+        self.allpass_output = S::ZERO;
+        self.thiran.outputs.iter_mut().for_each(|x| *x = S::ZERO);
+    }
+}
+
+// allpass interpolations are stateful so they cannot be used for taps
+fn stateless(interpolation: Interpolation) -> Interpolation {
+    match interpolation {
+        Interpolation::Allpass => Interpolation::Linear,
+        Interpolation::Thiran(_) => Interpolation::Lagrange,
+        interpolation => interpolation,
     }
+}
+
+// Thiran interpolation reads up to `order` samples past the length
+fn thiran_order(interpolation: Interpolation) -> usize {
+    match interpolation {
+        Interpolation::Thiran(order) => order,
+        _ => 0,
+    }
+}
+
+fn min_length(interpolation: Interpolation) -> f64 {
+    match interpolation {
+        Interpolation::None | Interpolation::Linear => 1.0,
+        Interpolation::Lagrange => 2.0,
+        Interpolation::Allpass => 1.5,
+        Interpolation::Thiran(order) => order as f64 + 0.5,
+    }
+}
 
-    pub fn output(&self) -> f64 {
-        self.buffer[self.read_ptr]
+// https://ccrma.stanford.edu/~jos/pasp/Thiran_Allpass_Interpolators.html
+struct Thiran<S> {
+    // integer part of the delay, read before the allpass
+    integer: usize,
+    // denominator coefficients, starting from a_0 = 1
+    coefficients: Vec<S>,
+    // past outputs, most recent first
+    outputs: Vec<S>,
+}
+
+impl<S: Sample> Thiran<S> {
+    fn new(interpolation: Interpolation, length: f64) -> Self {
+        let order = thiran_order(interpolation);
+        let mut thiran = Self {
+            integer: 0,
+            coefficients: vec![S::ZERO; order + 1],
+            outputs: vec![S::ZERO; order],
+        };
+        if let Interpolation::Thiran(_) = interpolation {
+            thiran.set_length(length);
+        }
+        thiran
     }
+
+    // the coefficients are updated in place and the past outputs are kept,
+    // so that the filter stays allpass while the length is changed every sample
+    fn set_length(&mut self, length: f64) {
+        let order = self.outputs.len();
+        // the delay of the allpass is kept in [N - 0.5, N + 0.5)
+        // where it is stable and has the best approximation
+        let n = order as f64;
+        let integer = (length - n + 0.5).floor();
+        let d = length - integer;
+        self.integer = integer as usize;
+        // a_0 is always 1, the general formula is 0 / 0 for integer delays
+        self.coefficients[0] = S::from_f64(1.0);
+        for k in 1..=order {
+            let binomial = (0..k).fold(1.0, |acc, i| acc * (n - i as f64) / (i + 1) as f64);
+            let product = (0..=order).fold(1.0, |acc, i| {
+                let i = i as f64;
+                acc * (d - n + i) / (d - n + k as f64 + i)
+            });
+            self.coefficients[k] =
+                S::from_f64(if k % 2 == 0 { 1.0 } else { -1.0 } * binomial * product);
+        }
+    }
+}
+
+// https://ccrma.stanford.edu/~jos/pasp/Time_Varying_Delay_Effects.html
+pub struct ModulatedDelay<S = f64> {
+    delay: Delay<S>,
+    center: f64,
+    depth: f64,
+    lfo: Lfo,
+}
+
+impl<S: Sample> ModulatedDelay<S> {
+    /// `center` and `depth` are in samples
+    pub fn new(center: f64, depth: f64, lfo: Lfo, interpolation: Interpolation) -> Self {
+        let mut delay = Delay::fractional(center + depth.abs(), interpolation);
+        delay.update_length(modulated_length(center, depth, &lfo, interpolation));
+        Self {
+            delay,
+            center,
+            depth,
+            lfo,
+        }
+    }
+}
+
+impl<S: Sample> DelayLine<S> for ModulatedDelay<S> {
+    fn input(&mut self, x: S) {
+        self.delay.input(x);
+        self.lfo.advance();
+        self.delay.update_length(modulated_length(
+            self.center,
+            self.depth,
+            &self.lfo,
+            self.delay.interpolation,
+        ));
+    }
+
+    fn output(&self) -> S {
+        self.delay.output()
+    }
+
+    fn reset(&mut self) {
+        self.delay.reset();
+        self.lfo.reset();
+        self.delay.update_length(modulated_length(
+            self.center,
+            self.depth,
+            &self.lfo,
+            self.delay.interpolation,
+        ));
+    }
+}
+
+fn modulated_length(center: f64, depth: f64, lfo: &Lfo, interpolation: Interpolation) -> f64 {
+    (center + depth * lfo.value()).max(min_length(interpolation))
 }
@@ -0,0 +1,92 @@
+// Enables flush-to-zero and denormals-are-zero modes of the FPU while alive,
+// so that subnormal numbers do not slow down processing.
+// The previous mode is restored when dropped.
+// The library already flushes its feedback paths with `Sample::flush_denormal`,
+// so this is an additional safety net for hosts.
+pub struct DenormalGuard {
+    previous: usize,
+}
+
+impl DenormalGuard {
+    /// # Safety
+    ///
+    /// Rust assumes the default floating-point environment, so no code other than processing
+    /// that tolerates flushed subnormals may run while the guard is alive.
+    /// Keep it in a scope around nothing but the processing call, and drop it on the same thread.
+    pub unsafe fn new() -> Self {
+        let previous = imp::get();
+        imp::set(previous | imp::FLUSH_TO_ZERO);
+        Self { previous }
+    }
+}
+
+impl Drop for DenormalGuard {
+    fn drop(&mut self) {
+        imp::set(self.previous);
+    }
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse"
+))]
+mod imp {
+    use std::arch::asm;
+
+    // FTZ and DAZ bits of MXCSR
+    pub const FLUSH_TO_ZERO: usize = 0x8040;
+
+    pub fn get() -> usize {
+        let mut csr = 0u32;
+        unsafe {
+            asm!("stmxcsr [{}]", in(reg) &mut csr, options(nostack, preserves_flags));
+        }
+        csr as usize
+    }
+
+    pub fn set(csr: usize) {
+        let csr = csr as u32;
+        unsafe {
+            asm!("ldmxcsr [{}]", in(reg) &csr, options(nostack, readonly, preserves_flags));
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod imp {
+    use std::arch::asm;
+
+    // FZ bit of FPCR
+    pub const FLUSH_TO_ZERO: usize = 1 << 24;
+
+    pub fn get() -> usize {
+        let fpcr: u64;
+        unsafe {
+            asm!("mrs {}, fpcr", out(reg) fpcr, options(nomem, nostack, preserves_flags));
+        }
+        fpcr as usize
+    }
+
+    pub fn set(fpcr: usize) {
+        unsafe {
+            asm!("msr fpcr, {}", in(reg) fpcr as u64, options(nomem, nostack, preserves_flags));
+        }
+    }
+}
+
+#[cfg(not(any(
+    all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "sse"
+    ),
+    target_arch = "aarch64"
+)))]
+mod imp {
+    pub const FLUSH_TO_ZERO: usize = 0;
+
+    pub fn get() -> usize {
+        0
+    }
+
+    pub fn set(_: usize) {}
+}
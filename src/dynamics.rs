@@ -0,0 +1,82 @@
+use crate::filter::Filter;
+use crate::sample::Sample;
+use std::marker::PhantomData;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Detection {
+    /// Follows the absolute value
+    Peak,
+    /// Follows the root mean square, smoothed with the attack and release times
+    Rms,
+}
+
+// attack-hold-release envelope follower outputting a non-negative amplitude
+pub struct EnvelopeFollower<S = f64> {
+    sample_rate: u32,
+    detection: Detection,
+    attack: f64,
+    release: f64,
+    hold: usize,
+    hold_counter: usize,
+    envelope: f64,
+    phantom: PhantomData<S>,
+}
+
+impl<S: Sample> EnvelopeFollower<S> {
+    /// Attack and release are the time constants in milliseconds
+    pub fn new(sample_rate: u32, attack_ms: f64, release_ms: f64, detection: Detection) -> Self {
+        Self {
+            sample_rate,
+            detection,
+            attack: coefficient_from_millis(sample_rate, attack_ms),
+            release: coefficient_from_millis(sample_rate, release_ms),
+            hold: 0,
+            hold_counter: 0,
+            envelope: 0.0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Keeps the envelope for `hold_ms` milliseconds after the input falls
+    /// before starting to release
+    pub fn set_hold(&mut self, hold_ms: f64) {
+        self.hold = (f64::from(self.sample_rate) * hold_ms.max(0.0) / 1000.0).round() as usize;
+    }
+}
+
+impl<S: Sample> Filter<S> for EnvelopeFollower<S> {
+    fn process_sample(&mut self, x: S) -> S {
+        let x = x.to_f64();
+        let level = match self.detection {
+            Detection::Peak => x.abs(),
+            Detection::Rms => x * x,
+        };
+        if level > self.envelope {
+            self.envelope = level + self.attack * (self.envelope - level);
+            self.hold_counter = self.hold;
+        } else if self.hold_counter > 0 {
+            self.hold_counter -= 1;
+        } else {
+            self.envelope = (level + self.release * (self.envelope - level)).flush_denormal();
+        }
+        S::from_f64(match self.detection {
+            Detection::Peak => self.envelope,
+            Detection::Rms => self.envelope.sqrt(),
+        })
+    }
+
+    fn reset(&mut self) {
+        self.hold_counter = 0;
+        self.envelope = 0.0;
+    }
+}
+
+// one-pole smoothing coefficient reaching 1 - 1/e of a step in the given time
+fn coefficient_from_millis(sample_rate: u32, ms: f64) -> f64 {
+    let samples = f64::from(sample_rate) * ms / 1000.0;
+    if samples > 0.0 {
+        (-1.0 / samples).exp()
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,123 @@
+use std::f64::consts::PI;
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn from_polar(r: f64, theta: f64) -> Self {
+        Self::new(r * theta.cos(), r * theta.sin())
+    }
+
+    pub fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+// iterative radix-2 Cooley-Tukey FFT with precomputed twiddle factors
+pub struct Fft {
+    twiddles: Vec<Complex>,
+    bit_reversed: Vec<usize>,
+}
+
+impl Fft {
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two());
+        let bits = size.trailing_zeros();
+        Self {
+            twiddles: (0..size / 2)
+                .map(|k| Complex::from_polar(1.0, -2.0 * PI * k as f64 / size as f64))
+                .collect(),
+            bit_reversed: (0..size)
+                .map(|i| {
+                    i.reverse_bits()
+                        .checked_shr(usize::BITS - bits)
+                        .unwrap_or(0)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.bit_reversed.len()
+    }
+
+    pub fn forward(&self, buf: &mut [Complex]) {
+        let n = self.size();
+        assert_eq!(buf.len(), n);
+
+        for (i, j) in self.bit_reversed.iter().enumerate() {
+            if i < *j {
+                buf.swap(i, *j);
+            }
+        }
+
+        let mut len = 2;
+        while len <= n {
+            let stride = n / len;
+            for start in (0..n).step_by(len) {
+                for k in 0..len / 2 {
+                    let w = self.twiddles[k * stride];
+                    let a = buf[start + k];
+                    let b = w * buf[start + k + len / 2];
+                    buf[start + k] = a + b;
+                    buf[start + k + len / 2] = a - b;
+                }
+            }
+            len *= 2;
+        }
+    }
+
+    /// Unnormalized inverse, so `inverse(forward(x))` is `size() * x`
+    pub fn inverse(&self, buf: &mut [Complex]) {
+        buf.iter_mut().for_each(|x| *x = x.conj());
+        self.forward(buf);
+        buf.iter_mut().for_each(|x| *x = x.conj());
+    }
+}
@@ -1,107 +1,545 @@
-use crate::delay::Delay;
+mod combinator;
 
-pub trait Filter {
-    fn process_sample(&mut self, x: f64) -> f64;
+pub use combinator::{FeedbackLoop, FilterList, Mix, Parallel, Series};
+
+use crate::delay::{Delay, DelayLine};
+use crate::sample::Sample;
+
+pub trait Filter<S: Sample = f64> {
+    fn process_sample(&mut self, x: S) -> S;
+
+    /// `input` and `output` must have the same length
+    fn process_block(&mut self, input: &[S], output: &mut [S]) {
+        assert_eq!(input.len(), output.len());
+        for (x, y) in input.iter().zip(output) {
+            *y = self.process_sample(*x);
+        }
+    }
+
+    fn process_block_in_place(&mut self, buffer: &mut [S]) {
+        for x in buffer {
+            *x = self.process_sample(*x);
+        }
+    }
+
+    /// Clears the internal state as if no sample has been processed
+    fn reset(&mut self);
+}
+
+impl<S: Sample, F: Filter<S> + ?Sized> Filter<S> for Box<F> {
+    fn process_sample(&mut self, x: S) -> S {
+        (**self).process_sample(x)
+    }
+
+    fn process_block(&mut self, input: &[S], output: &mut [S]) {
+        (**self).process_block(input, output)
+    }
+
+    fn process_block_in_place(&mut self, buffer: &mut [S]) {
+        (**self).process_block_in_place(buffer)
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
 }
 
 // https://ccrma.stanford.edu/~jos/pasp/Allpass_Two_Combs.html
-pub struct Allpass {
-    am: f64,
-    b0: f64,
-    zm: Delay,
+pub struct Allpass<S = f64, D = Delay<S>> {
+    am: S,
+    b0: S,
+    zm: D,
 }
 
-impl Allpass {
-    pub fn new(am: f64, b0: f64, m: usize) -> Self {
+impl<S: Sample, D: DelayLine<S>> Allpass<S, D> {
+    pub fn new(am: f64, b0: f64, zm: D) -> Self {
         Self {
-            am,
-            b0,
-            zm: Delay::new(m),
+            am: S::from_f64(am),
+            b0: S::from_f64(b0),
+            zm,
         }
     }
+
+    pub fn delay(&self) -> &D {
+        &self.zm
+    }
+
+    pub fn delay_mut(&mut self) -> &mut D {
+        &mut self.zm
+    }
 }
 
-impl Filter for Allpass {
-    fn process_sample(&mut self, x: f64) -> f64 {
+impl<S: Sample, D: DelayLine<S>> Filter<S> for Allpass<S, D> {
+    fn process_sample(&mut self, x: S) -> S {
         let v = x - self.am * self.zm.output();
         let output = self.b0 * v + self.zm.output();
         self.zm.input(v);
         output
     }
+
+    fn reset(&mut self) {
+        self.zm.reset();
+    }
+}
+
+// Gardner, W. G. (1992). The virtual acoustic room.
+// the inner filter is placed in series with the delay, so the whole filter
+// stays allpass as long as the inner filter is allpass
+pub struct NestedAllpass<F, S = f64, D = Delay<S>> {
+    g: S,
+    zm: D,
+    inner: F,
+}
+
+impl<S: Sample, D: DelayLine<S>, F: Filter<S>> NestedAllpass<F, S, D> {
+    pub fn new(g: f64, zm: D, inner: F) -> Self {
+        Self {
+            g: S::from_f64(g),
+            zm,
+            inner,
+        }
+    }
+
+    /// Outer delay, the inner filter is left untouched
+    pub fn delay_mut(&mut self) -> &mut D {
+        &mut self.zm
+    }
+}
+
+impl<S: Sample, D: DelayLine<S>> NestedAllpass<Allpass<S, D>, S, D> {
+    pub fn single(g: f64, zm: D, inner_g: f64, inner_zm: D) -> Self {
+        Self::new(g, zm, Allpass::new(-inner_g, -inner_g, inner_zm))
+    }
+}
+
+impl<S: Sample, D: DelayLine<S>> NestedAllpass<Series<(Allpass<S, D>, Allpass<S, D>)>, S, D> {
+    /// Nests two allpasses in series
+    pub fn double(g: f64, zm: D, inner_gs: (f64, f64), inner_zms: (D, D)) -> Self {
+        Self::new(
+            g,
+            zm,
+            Series::new((
+                Allpass::new(-inner_gs.0, -inner_gs.0, inner_zms.0),
+                Allpass::new(-inner_gs.1, -inner_gs.1, inner_zms.1),
+            )),
+        )
+    }
+}
+
+impl<S: Sample, D: DelayLine<S>, F: Filter<S>> Filter<S> for NestedAllpass<F, S, D> {
+    fn process_sample(&mut self, x: S) -> S {
+        let d = self.inner.process_sample(self.zm.output());
+        let w = x + self.g * d;
+        self.zm.input(w);
+        d - self.g * w
+    }
+
+    fn reset(&mut self) {
+        self.zm.reset();
+        self.inner.reset();
+    }
 }
 
-pub struct FeedforwardComb {
-    b0: f64,
-    bm: f64,
-    zm: Delay,
+pub struct FeedforwardComb<S = f64, D = Delay<S>> {
+    b0: S,
+    bm: S,
+    zm: D,
 }
 
-impl FeedforwardComb {
-    pub fn new(b0: f64, bm: f64, m: usize) -> Self {
+impl<S: Sample, D: DelayLine<S>> FeedforwardComb<S, D> {
+    pub fn new(b0: f64, bm: f64, zm: D) -> Self {
         Self {
-            b0,
-            bm,
-            zm: Delay::new(m),
+            b0: S::from_f64(b0),
+            bm: S::from_f64(bm),
+            zm,
         }
     }
+
+    pub fn delay_mut(&mut self) -> &mut D {
+        &mut self.zm
+    }
 }
 
-impl Filter for FeedforwardComb {
-    fn process_sample(&mut self, x: f64) -> f64 {
+impl<S: Sample, D: DelayLine<S>> Filter<S> for FeedforwardComb<S, D> {
+    fn process_sample(&mut self, x: S) -> S {
         let output = self.b0 * x + self.bm * self.zm.output();
         self.zm.input(x);
         output
     }
+
+    fn reset(&mut self) {
+        self.zm.reset();
+    }
 }
 
 // https://ccrma.stanford.edu/~jos/pasp/Feedback_Comb_Filters.html
-pub struct FeedbackComb {
-    am: f64,
-    zm: Delay,
+pub struct FeedbackComb<S = f64, D = Delay<S>> {
+    am: S,
+    zm: D,
 }
 
-impl FeedbackComb {
-    pub fn new(am: f64, m: usize) -> Self {
+impl<S: Sample, D: DelayLine<S>> FeedbackComb<S, D> {
+    pub fn new(am: f64, zm: D) -> Self {
         Self {
-            am,
-            zm: Delay::new(m),
+            am: S::from_f64(am),
+            zm,
         }
     }
+
+    pub fn delay_mut(&mut self) -> &mut D {
+        &mut self.zm
+    }
 }
 
-impl Filter for FeedbackComb {
-    fn process_sample(&mut self, x: f64) -> f64 {
+impl<S: Sample, D: DelayLine<S>> Filter<S> for FeedbackComb<S, D> {
+    fn process_sample(&mut self, x: S) -> S {
         let v = x - self.am * self.zm.output();
         self.zm.input(v);
         v
     }
+
+    fn reset(&mut self) {
+        self.zm.reset();
+    }
 }
 
 // https://ccrma.stanford.edu/~jos/pasp/Lowpass_Feedback_Comb_Filter.html
-pub struct LowpassFeedbackComb {
-    f: f64,
-    d: f64,
-    z1: Delay,
-    zn: Delay,
+pub struct LowpassFeedbackComb<S = f64, D = Delay<S>> {
+    f: S,
+    lowpass: OnePoleLowpass<S>,
+    zn: D,
 }
 
-impl LowpassFeedbackComb {
-    pub fn new(f: f64, d: f64, n: usize) -> Self {
+impl<S: Sample, D: DelayLine<S>> LowpassFeedbackComb<S, D> {
+    pub fn new(f: f64, d: f64, zn: D) -> Self {
         Self {
-            f,
-            d,
-            z1: Delay::new(1),
-            zn: Delay::new(n),
+            f: S::from_f64(f),
+            lowpass: OnePoleLowpass::from_pole(d),
+            zn,
         }
     }
+
+    pub fn set_feedback(&mut self, f: f64) {
+        self.f = S::from_f64(f);
+    }
+
+    pub fn delay(&self) -> &D {
+        &self.zn
+    }
+
+    pub fn delay_mut(&mut self) -> &mut D {
+        &mut self.zn
+    }
 }
 
-impl Filter for LowpassFeedbackComb {
-    fn process_sample(&mut self, x: f64) -> f64 {
-        let filterstore = (1.0 - self.d) * self.zn.output() + self.d * self.z1.output();
+impl<S: Sample, D: DelayLine<S>> Filter<S> for LowpassFeedbackComb<S, D> {
+    fn process_sample(&mut self, x: S) -> S {
+        let filterstore = self.lowpass.process_sample(self.zn.output());
         let y = x + self.f * filterstore;
-        self.z1.input(filterstore);
         self.zn.input(y);
         y
     }
+
+    fn reset(&mut self) {
+        self.lowpass.reset();
+        self.zn.reset();
+    }
+}
+
+// https://ccrma.stanford.edu/~jos/fp/One_Pole.html
+pub struct OnePoleLowpass<S = f64> {
+    b0: S,
+    pole: S,
+    y1: S,
+}
+
+impl<S: Sample> OnePoleLowpass<S> {
+    pub fn new(sample_rate: u32, cutoff: f64) -> Self {
+        Self::from_pole(pole_from_cutoff(sample_rate, cutoff))
+    }
+
+    pub fn from_pole(pole: f64) -> Self {
+        Self {
+            b0: S::from_f64(1.0 - pole),
+            pole: S::from_f64(pole),
+            y1: S::ZERO,
+        }
+    }
+}
+
+impl<S: Sample> Filter<S> for OnePoleLowpass<S> {
+    fn process_sample(&mut self, x: S) -> S {
+        self.y1 = (self.b0 * x + self.pole * self.y1).flush_denormal();
+        self.y1
+    }
+
+    fn reset(&mut self) {
+        self.y1 = S::ZERO;
+    }
+}
+
+pub struct OnePoleHighpass<S = f64> {
+    b0: S,
+    pole: S,
+    x1: S,
+    y1: S,
+}
+
+impl<S: Sample> OnePoleHighpass<S> {
+    pub fn new(sample_rate: u32, cutoff: f64) -> Self {
+        Self::from_pole(pole_from_cutoff(sample_rate, cutoff))
+    }
+
+    pub fn from_pole(pole: f64) -> Self {
+        Self {
+            b0: S::from_f64(0.5 * (1.0 + pole)),
+            pole: S::from_f64(pole),
+            x1: S::ZERO,
+            y1: S::ZERO,
+        }
+    }
+}
+
+impl<S: Sample> Filter<S> for OnePoleHighpass<S> {
+    fn process_sample(&mut self, x: S) -> S {
+        self.y1 = (self.b0 * (x - self.x1) + self.pole * self.y1).flush_denormal();
+        self.x1 = x;
+        self.y1
+    }
+
+    fn reset(&mut self) {
+        self.x1 = S::ZERO;
+        self.y1 = S::ZERO;
+    }
+}
+
+// https://ccrma.stanford.edu/~jos/fp/Leaky_Integrator.html
+pub struct LeakyIntegrator<S = f64> {
+    leak: S,
+    y1: S,
+}
+
+impl<S: Sample> LeakyIntegrator<S> {
+    pub fn new(sample_rate: u32, cutoff: f64) -> Self {
+        Self::from_leak(pole_from_cutoff(sample_rate, cutoff))
+    }
+
+    pub fn from_leak(leak: f64) -> Self {
+        Self {
+            leak: S::from_f64(leak),
+            y1: S::ZERO,
+        }
+    }
+}
+
+impl<S: Sample> Filter<S> for LeakyIntegrator<S> {
+    fn process_sample(&mut self, x: S) -> S {
+        self.y1 = (x + self.leak * self.y1).flush_denormal();
+        self.y1
+    }
+
+    fn reset(&mut self) {
+        self.y1 = S::ZERO;
+    }
+}
+
+// https://ccrma.stanford.edu/~jos/fp/DC_Blocker.html
+pub struct DcBlocker<S = f64> {
+    r: S,
+    x1: S,
+    y1: S,
+}
+
+impl<S: Sample> DcBlocker<S> {
+    pub fn new(sample_rate: u32, cutoff: f64) -> Self {
+        Self::from_pole(pole_from_cutoff(sample_rate, cutoff))
+    }
+
+    pub fn from_pole(r: f64) -> Self {
+        Self {
+            r: S::from_f64(r),
+            x1: S::ZERO,
+            y1: S::ZERO,
+        }
+    }
+}
+
+impl<S: Sample> Filter<S> for DcBlocker<S> {
+    fn process_sample(&mut self, x: S) -> S {
+        self.y1 = (x - self.x1 + self.r * self.y1).flush_denormal();
+        self.x1 = x;
+        self.y1
+    }
+
+    fn reset(&mut self) {
+        self.x1 = S::ZERO;
+        self.y1 = S::ZERO;
+    }
+}
+
+// first-order shelving pair whose gains are symmetric in dB around the pivot,
+// https://ccrma.stanford.edu/~jos/fp/Bilinear_Transformation.html
+pub struct Tilt<S = f64> {
+    b0: S,
+    b1: S,
+    a1: S,
+    x1: S,
+    y1: S,
+}
+
+impl<S: Sample> Tilt<S> {
+    /// Boosts high frequencies by `gain_db / 2` and cuts low frequencies by the same amount,
+    /// so positive gains brighten and negative gains darken. The pivot is left at 0 dB
+    pub fn new(sample_rate: u32, pivot: f64, gain_db: f64) -> Self {
+        // analog prototype: H(s) = sqrt(g) (s + 1 / sqrt(g)) / (s + sqrt(g)), normalized to the pivot
+        let g = f64::powf(10.0, gain_db / 20.0);
+        let k = (std::f64::consts::PI * pivot / f64::from(sample_rate)).tan();
+        let (a, b) = (1.0 / g.sqrt(), g.sqrt());
+        let a0 = 1.0 + b * k;
+        Self {
+            b0: S::from_f64(g.sqrt() * (1.0 + a * k) / a0),
+            b1: S::from_f64(g.sqrt() * (a * k - 1.0) / a0),
+            a1: S::from_f64((b * k - 1.0) / a0),
+            x1: S::ZERO,
+            y1: S::ZERO,
+        }
+    }
+}
+
+impl<S: Sample> Filter<S> for Tilt<S> {
+    fn process_sample(&mut self, x: S) -> S {
+        self.y1 = (self.b0 * x + self.b1 * self.x1 - self.a1 * self.y1).flush_denormal();
+        self.x1 = x;
+        self.y1
+    }
+
+    fn reset(&mut self) {
+        self.x1 = S::ZERO;
+        self.y1 = S::ZERO;
+    }
+}
+
+pub(crate) fn pole_from_cutoff(sample_rate: u32, cutoff: f64) -> f64 {
+    (-2.0 * std::f64::consts::PI * cutoff / f64::from(sample_rate)).exp()
+}
+
+// https://www.w3.org/TR/audio-eq-cookbook/
+pub struct Biquad<S = f64> {
+    b0: S,
+    b1: S,
+    b2: S,
+    a1: S,
+    a2: S,
+    s1: S,
+    s2: S,
+}
+
+impl<S: Sample> Biquad<S> {
+    pub fn from_coefficients(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Self {
+        Self {
+            b0: S::from_f64(b0 / a0),
+            b1: S::from_f64(b1 / a0),
+            b2: S::from_f64(b2 / a0),
+            a1: S::from_f64(a1 / a0),
+            a2: S::from_f64(a2 / a0),
+            s1: S::ZERO,
+            s2: S::ZERO,
+        }
+    }
+
+    pub fn lowpass(sample_rate: u32, freq: f64, q: f64) -> Self {
+        let (cos, alpha) = cos_and_alpha(sample_rate, freq, q);
+        Self::from_coefficients(
+            (1.0 - cos) / 2.0,
+            1.0 - cos,
+            (1.0 - cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    pub fn highpass(sample_rate: u32, freq: f64, q: f64) -> Self {
+        let (cos, alpha) = cos_and_alpha(sample_rate, freq, q);
+        Self::from_coefficients(
+            (1.0 + cos) / 2.0,
+            -(1.0 + cos),
+            (1.0 + cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    // constant 0 dB peak gain
+    pub fn bandpass(sample_rate: u32, freq: f64, q: f64) -> Self {
+        let (cos, alpha) = cos_and_alpha(sample_rate, freq, q);
+        Self::from_coefficients(alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    pub fn notch(sample_rate: u32, freq: f64, q: f64) -> Self {
+        let (cos, alpha) = cos_and_alpha(sample_rate, freq, q);
+        Self::from_coefficients(1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    pub fn peaking(sample_rate: u32, freq: f64, q: f64, gain_db: f64) -> Self {
+        let (cos, alpha) = cos_and_alpha(sample_rate, freq, q);
+        let a = f64::powf(10.0, gain_db / 40.0);
+        Self::from_coefficients(
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        )
+    }
+
+    pub fn low_shelf(sample_rate: u32, freq: f64, q: f64, gain_db: f64) -> Self {
+        let (cos, alpha) = cos_and_alpha(sample_rate, freq, q);
+        let a = f64::powf(10.0, gain_db / 40.0);
+        let k = 2.0 * a.sqrt() * alpha;
+        Self::from_coefficients(
+            a * ((a + 1.0) - (a - 1.0) * cos + k),
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+            a * ((a + 1.0) - (a - 1.0) * cos - k),
+            (a + 1.0) + (a - 1.0) * cos + k,
+            -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+            (a + 1.0) + (a - 1.0) * cos - k,
+        )
+    }
+
+    pub fn high_shelf(sample_rate: u32, freq: f64, q: f64, gain_db: f64) -> Self {
+        let (cos, alpha) = cos_and_alpha(sample_rate, freq, q);
+        let a = f64::powf(10.0, gain_db / 40.0);
+        let k = 2.0 * a.sqrt() * alpha;
+        Self::from_coefficients(
+            a * ((a + 1.0) + (a - 1.0) * cos + k),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - k),
+            (a + 1.0) - (a - 1.0) * cos + k,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - k,
+        )
+    }
+}
+
+impl<S: Sample> Filter<S> for Biquad<S> {
+    // transposed direct form II
+    fn process_sample(&mut self, x: S) -> S {
+        let y = self.b0 * x + self.s1;
+        self.s1 = (self.b1 * x - self.a1 * y + self.s2).flush_denormal();
+        self.s2 = (self.b2 * x - self.a2 * y).flush_denormal();
+        y
+    }
+
+    fn reset(&mut self) {
+        self.s1 = S::ZERO;
+        self.s2 = S::ZERO;
+    }
+}
+
+fn cos_and_alpha(sample_rate: u32, freq: f64, q: f64) -> (f64, f64) {
+    let w0 = 2.0 * std::f64::consts::PI * freq / f64::from(sample_rate);
+    (w0.cos(), w0.sin() / (2.0 * q))
 }
@@ -0,0 +1,224 @@
+use super::Filter;
+use crate::sample::Sample;
+
+// group of filters that combinators can treat uniformly
+// implemented for tuples, arrays and vectors of filters
+pub trait FilterList<S: Sample = f64> {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // feeds the output of each filter to the next one
+    fn process_series(&mut self, x: S) -> S;
+
+    // feeds `x` to every filter and sums their outputs weighted by `gains`
+    fn process_parallel(&mut self, x: S, gains: &[S]) -> S;
+
+    fn reset(&mut self);
+}
+
+impl<S: Sample, F: Filter<S>> FilterList<S> for [F] {
+    fn len(&self) -> usize {
+        <[F]>::len(self)
+    }
+
+    fn process_series(&mut self, x: S) -> S {
+        self.iter_mut().fold(x, |acc, f| f.process_sample(acc))
+    }
+
+    fn process_parallel(&mut self, x: S, gains: &[S]) -> S {
+        self.iter_mut()
+            .zip(gains)
+            .map(|(f, g)| *g * f.process_sample(x))
+            .sum()
+    }
+
+    fn reset(&mut self) {
+        self.iter_mut().for_each(Filter::reset);
+    }
+}
+
+impl<S: Sample, F: Filter<S>, const N: usize> FilterList<S> for [F; N] {
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn process_series(&mut self, x: S) -> S {
+        self[..].process_series(x)
+    }
+
+    fn process_parallel(&mut self, x: S, gains: &[S]) -> S {
+        self[..].process_parallel(x, gains)
+    }
+
+    fn reset(&mut self) {
+        FilterList::reset(&mut self[..]);
+    }
+}
+
+impl<S: Sample, F: Filter<S>> FilterList<S> for Vec<F> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn process_series(&mut self, x: S) -> S {
+        self[..].process_series(x)
+    }
+
+    fn process_parallel(&mut self, x: S, gains: &[S]) -> S {
+        self[..].process_parallel(x, gains)
+    }
+
+    fn reset(&mut self) {
+        FilterList::reset(&mut self[..]);
+    }
+}
+
+macro_rules! impl_filter_list_for_tuple {
+    ($len:expr; $($f:ident $i:tt),*) => {
+        impl<S: Sample, $($f: Filter<S>),*> FilterList<S> for ($($f,)*) {
+            fn len(&self) -> usize {
+                $len
+            }
+
+            fn process_series(&mut self, x: S) -> S {
+                $(let x = self.$i.process_sample(x);)*
+                x
+            }
+
+            fn process_parallel(&mut self, x: S, gains: &[S]) -> S {
+                let mut y = S::ZERO;
+                $(y += gains[$i] * self.$i.process_sample(x);)*
+                y
+            }
+
+            fn reset(&mut self) {
+                $(self.$i.reset();)*
+            }
+        }
+    };
+}
+
+impl_filter_list_for_tuple!(2; A 0, B 1);
+impl_filter_list_for_tuple!(3; A 0, B 1, C 2);
+impl_filter_list_for_tuple!(4; A 0, B 1, C 2, D 3);
+impl_filter_list_for_tuple!(5; A 0, B 1, C 2, D 3, E 4);
+impl_filter_list_for_tuple!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+impl_filter_list_for_tuple!(7; A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_filter_list_for_tuple!(8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+// feeds the output of each filter to the next one
+pub struct Series<L> {
+    filters: L,
+}
+
+impl<L> Series<L> {
+    pub fn new(filters: L) -> Self {
+        Self { filters }
+    }
+}
+
+impl<S: Sample, L: FilterList<S>> Filter<S> for Series<L> {
+    fn process_sample(&mut self, x: S) -> S {
+        self.filters.process_series(x)
+    }
+
+    fn reset(&mut self) {
+        self.filters.reset();
+    }
+}
+
+pub struct Parallel<L, S = f64> {
+    filters: L,
+    gains: Vec<S>,
+}
+
+impl<S: Sample, L: FilterList<S>> Parallel<L, S> {
+    /// `gains` must have one element per filter
+    pub fn new(filters: L, gains: &[f64]) -> Self {
+        assert_eq!(filters.len(), gains.len());
+        Self {
+            filters,
+            gains: gains.iter().map(|g| S::from_f64(*g)).collect(),
+        }
+    }
+
+    /// Sums the outputs with unity gains
+    pub fn with_unity_gains(filters: L) -> Self {
+        let gains = vec![1.0; filters.len()];
+        Self::new(filters, &gains)
+    }
+}
+
+impl<S: Sample, L: FilterList<S>> Filter<S> for Parallel<L, S> {
+    fn process_sample(&mut self, x: S) -> S {
+        self.filters.process_parallel(x, &self.gains)
+    }
+
+    fn reset(&mut self) {
+        self.filters.reset();
+    }
+}
+
+// the loop has an implicit one-sample delay so that it is realizable
+// even if neither filter has a delay
+pub struct FeedbackLoop<F, G, S = f64> {
+    forward: F,
+    feedback: G,
+    gain: S,
+    y1: S,
+}
+
+impl<S: Sample, F: Filter<S>, G: Filter<S>> FeedbackLoop<F, G, S> {
+    pub fn new(forward: F, feedback: G, gain: f64) -> Self {
+        Self {
+            forward,
+            feedback,
+            gain: S::from_f64(gain),
+            y1: S::ZERO,
+        }
+    }
+}
+
+impl<S: Sample, F: Filter<S>, G: Filter<S>> Filter<S> for FeedbackLoop<F, G, S> {
+    fn process_sample(&mut self, x: S) -> S {
+        let v = x + self.gain * self.feedback.process_sample(self.y1);
+        self.y1 = self.forward.process_sample(v).flush_denormal();
+        self.y1
+    }
+
+    fn reset(&mut self) {
+        self.forward.reset();
+        self.feedback.reset();
+        self.y1 = S::ZERO;
+    }
+}
+
+// dry/wet mix of a filter and its input
+pub struct Mix<F, S = f64> {
+    filter: F,
+    dry: S,
+    wet: S,
+}
+
+impl<S: Sample, F: Filter<S>> Mix<F, S> {
+    pub fn new(filter: F, dry: f64, wet: f64) -> Self {
+        Self {
+            filter,
+            dry: S::from_f64(dry),
+            wet: S::from_f64(wet),
+        }
+    }
+}
+
+impl<S: Sample, F: Filter<S>> Filter<S> for Mix<F, S> {
+    fn process_sample(&mut self, x: S) -> S {
+        self.dry * x + self.wet * self.filter.process_sample(x)
+    }
+
+    fn reset(&mut self) {
+        self.filter.reset();
+    }
+}
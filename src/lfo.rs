@@ -0,0 +1,78 @@
+use crate::rng::Rng;
+use std::f64::consts::PI;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Waveform {
+    Sine,
+    Triangle,
+    /// Linearly interpolated random walk, seeded for reproducibility
+    RandomWalk(u64),
+}
+
+// low frequency oscillator outputting values in [-1, 1]
+pub struct Lfo {
+    waveform: Waveform,
+    initial_phase: f64,
+    phase: f64,
+    increment: f64,
+    rng: Rng,
+    from: f64,
+    to: f64,
+}
+
+impl Lfo {
+    pub fn new(sample_rate: u32, waveform: Waveform, rate: f64, phase: f64) -> Self {
+        let mut lfo = Self {
+            waveform,
+            initial_phase: phase.rem_euclid(1.0),
+            phase: 0.0,
+            increment: rate / f64::from(sample_rate),
+            rng: Rng::new(0),
+            from: 0.0,
+            to: 0.0,
+        };
+        lfo.reset();
+        lfo
+    }
+
+    pub fn reset(&mut self) {
+        self.phase = self.initial_phase;
+        self.rng = Rng::new(match self.waveform {
+            Waveform::RandomWalk(seed) => seed,
+            _ => 0,
+        });
+        self.from = self.rng.next_bipolar();
+        self.to = random_step(&mut self.rng, self.from);
+    }
+
+    pub fn value(&self) -> f64 {
+        match self.waveform {
+            Waveform::Sine => (2.0 * PI * self.phase).sin(),
+            Waveform::Triangle => 4.0 * ((self.phase + 0.75).fract() - 0.5).abs() - 1.0,
+            Waveform::RandomWalk(_) => self.from + (self.to - self.from) * self.phase,
+        }
+    }
+
+    pub fn advance(&mut self) {
+        self.phase += self.increment;
+        if self.phase >= 1.0 {
+            self.phase -= self.phase.floor();
+            if let Waveform::RandomWalk(_) = self.waveform {
+                self.from = self.to;
+                self.to = random_step(&mut self.rng, self.from);
+            }
+        }
+    }
+}
+
+// moves by at most half of the full range, reflecting at the boundaries
+fn random_step(rng: &mut Rng, from: f64) -> f64 {
+    let to = from + rng.next_bipolar();
+    if to > 1.0 {
+        2.0 - to
+    } else if to < -1.0 {
+        -2.0 - to
+    } else {
+        to
+    }
+}
@@ -1,5 +1,15 @@
-mod delay;
-mod filter;
+pub mod analysis;
+pub mod delay;
+pub mod denormal;
+pub mod dynamics;
+mod fft;
+pub mod filter;
+pub mod lfo;
+pub mod matrix;
 mod reverb;
+mod rng;
+pub mod sample;
+pub mod wav;
 
 pub use reverb::*;
+pub use sample::Sample;
@@ -1,8 +1,10 @@
+use accent::denormal::DenormalGuard;
+use accent::matrix::{Circulant, FeedbackMatrix, Hadamard, Householder, RandomOrthogonal};
+use accent::wav;
 use accent::*;
 use anyhow::Result;
 use clap::{App, AppSettings, Arg, SubCommand};
-use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
-use itertools::Itertools;
+use hound::{SampleFormat, WavSpec, WavWriter};
 
 fn main() -> Result<()> {
     let arg_input = Arg::with_name("input")
@@ -74,6 +76,142 @@ fn main() -> Result<()> {
                 .arg(Arg::with_name("wet").long("wet").default_value("1"))
                 .arg(Arg::with_name("dry").long("dry").default_value("0")),
         )
+        .subcommand(
+            SubCommand::with_name("fdn")
+                .about("Feedback delay network")
+                .arg(&arg_input)
+                .arg(Arg::with_name("t60").long("t60").default_value("2"))
+                .arg(
+                    Arg::with_name("order")
+                        .long("order")
+                        .possible_values(&["4", "8", "16"])
+                        .default_value("8"),
+                )
+                .arg(
+                    Arg::with_name("matrix")
+                        .long("matrix")
+                        .possible_values(&["hadamard", "householder", "random", "circulant"])
+                        .default_value("hadamard"),
+                )
+                .arg(
+                    Arg::with_name("delays")
+                        .long("delays")
+                        .help("Comma-separated delay lengths in ms, one for each line")
+                        .takes_value(true)
+                        .use_delimiter(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("dattorro")
+                .about("Dattorro's plate")
+                .arg(&arg_input)
+                .arg(
+                    Arg::with_name("predelay")
+                        .long("predelay")
+                        .help("Pre-delay in ms")
+                        .default_value("0"),
+                )
+                .arg(
+                    Arg::with_name("bandwidth")
+                        .long("bandwidth")
+                        .default_value("0.9995"),
+                )
+                .arg(
+                    Arg::with_name("damping")
+                        .long("damping")
+                        .default_value("0.0005"),
+                )
+                .arg(Arg::with_name("decay").long("decay").default_value("0.5")),
+        )
+        .subcommand(
+            SubCommand::with_name("convolve")
+                .about("Convolution with an impulse response")
+                .arg(&arg_input)
+                .arg(
+                    Arg::with_name("ir")
+                        .long("ir")
+                        .help("Impulse response WAV file with 1, 2 or 4 (true stereo) channels")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("zita-rev1")
+                .about("Zita-rev1 style FDN")
+                .arg(&arg_input)
+                .arg(
+                    Arg::with_name("predelay")
+                        .long("predelay")
+                        .help("Pre-delay in ms")
+                        .default_value("40"),
+                )
+                .arg(
+                    Arg::with_name("crossover")
+                        .long("crossover")
+                        .help("Crossover frequency between the low and mid bands in Hz")
+                        .default_value("200"),
+                )
+                .arg(
+                    Arg::with_name("rt-low")
+                        .long("rt-low")
+                        .help("RT60 of the low band in seconds")
+                        .default_value("3"),
+                )
+                .arg(
+                    Arg::with_name("rt-mid")
+                        .long("rt-mid")
+                        .help("RT60 of the mid band in seconds")
+                        .default_value("2"),
+                )
+                .arg(
+                    Arg::with_name("hf-damping")
+                        .long("hf-damping")
+                        .help("Frequency in Hz where the RT60 is half of the mid band")
+                        .default_value("6000"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("gardner")
+                .about("Gardner's room")
+                .arg(&arg_input)
+                .arg(
+                    Arg::with_name("room")
+                        .long("room")
+                        .possible_values(&["small", "medium", "large"])
+                        .default_value("medium"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("moorer")
+                .about("Moorer's reverb with early reflections")
+                .arg(&arg_input)
+                .arg(
+                    Arg::with_name("predelay")
+                        .long("predelay")
+                        .help("Pre-delay in ms")
+                        .default_value("0"),
+                )
+                .arg(Arg::with_name("t60").long("t60").default_value("2"))
+                .arg(
+                    Arg::with_name("balance")
+                        .long("balance")
+                        .help("Mix from only the early reflections at 0 to only the late reverberation at 1")
+                        .default_value("0.5"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("velvet")
+                .about("Filtered velvet noise")
+                .arg(&arg_input)
+                .arg(
+                    Arg::with_name("density")
+                        .long("density")
+                        .help("Pulses per second")
+                        .default_value("1500"),
+                )
+                .arg(Arg::with_name("t60").long("t60").default_value("1.5"))
+                .arg(Arg::with_name("seed").long("seed").default_value("0")),
+        )
         .get_matches();
 
     let input = match app_m.subcommand() {
@@ -86,9 +224,9 @@ fn main() -> Result<()> {
     let gain_db = app_m.value_of("gain").unwrap().parse::<f64>()?;
     let gain = f64::powf(10.0, gain_db / 20.0);
 
-    let mut reader = WavReader::open(input)?;
-    let input_channels = reader.spec().channels;
-    let sample_rate = reader.spec().sample_rate;
+    let (spec, samples) = wav::read(input)?;
+    let input_channels = spec.channels;
+    let sample_rate = spec.sample_rate;
 
     let mut reverb: Box<dyn Reverb> = match app_m.subcommand() {
         ("jcrev", Some(_)) => Box::new(JCRev::new(sample_rate)),
@@ -113,35 +251,84 @@ fn main() -> Result<()> {
             sub_m.value_of("wet").unwrap().parse()?,
             sub_m.value_of("dry").unwrap().parse()?,
         )),
+        ("fdn", Some(sub_m)) => {
+            let order = sub_m.value_of("order").unwrap().parse()?;
+            let delays = match sub_m.values_of("delays") {
+                Some(values) => values.map(str::parse).collect::<Result<Vec<_>, _>>()?,
+                None => match default_fdn_delays(order) {
+                    Some(delays) => delays.to_vec(),
+                    None => anyhow::bail!("no default delays for order {}, give --delays", order),
+                },
+            };
+            if delays.len() != order {
+                anyhow::bail!("{} delays are given for order {}", delays.len(), order);
+            }
+            let matrix: Box<dyn FeedbackMatrix> = match sub_m.value_of("matrix").unwrap() {
+                "hadamard" => Box::new(Hadamard::new(order)),
+                "householder" => Box::new(Householder::new(order)),
+                "random" => Box::new(RandomOrthogonal::new(order, 0)),
+                "circulant" => Box::new(Circulant::random(order, 0)),
+                _ => unreachable!(),
+            };
+            Box::new(FDN::new(
+                sample_rate,
+                matrix,
+                &delays,
+                sub_m.value_of("t60").unwrap().parse()?,
+            ))
+        }
+        ("dattorro", Some(sub_m)) => Box::new(Dattorro::new(
+            sample_rate,
+            sub_m.value_of("predelay").unwrap().parse()?,
+            sub_m.value_of("bandwidth").unwrap().parse()?,
+            sub_m.value_of("damping").unwrap().parse()?,
+            sub_m.value_of("decay").unwrap().parse()?,
+        )),
+        ("convolve", Some(sub_m)) => Box::new(Convolution::from_wav(
+            sub_m.value_of("ir").unwrap(),
+            sample_rate,
+        )?),
+        ("zita-rev1", Some(sub_m)) => {
+            let hf_damping = sub_m.value_of("hf-damping").unwrap().parse()?;
+            if hf_damping <= 0.0 {
+                anyhow::bail!("--hf-damping must be positive, but {} is given", hf_damping);
+            }
+            Box::new(ZitaRev1::new(
+                sample_rate,
+                sub_m.value_of("predelay").unwrap().parse()?,
+                sub_m.value_of("crossover").unwrap().parse()?,
+                sub_m.value_of("rt-low").unwrap().parse()?,
+                sub_m.value_of("rt-mid").unwrap().parse()?,
+                hf_damping,
+            ))
+        }
+        ("gardner", Some(sub_m)) => {
+            let room = match sub_m.value_of("room").unwrap() {
+                "small" => GardnerRoom::Small,
+                "medium" => GardnerRoom::Medium,
+                "large" => GardnerRoom::Large,
+                _ => unreachable!(),
+            };
+            Box::new(Gardner::new(sample_rate, room))
+        }
+        ("moorer", Some(sub_m)) => Box::new(Moorer::new(
+            sample_rate,
+            sub_m.value_of("predelay").unwrap().parse()?,
+            sub_m.value_of("t60").unwrap().parse()?,
+            sub_m.value_of("balance").unwrap().parse()?,
+        )),
+        ("velvet", Some(sub_m)) => Box::new(VelvetNoise::new(
+            sample_rate,
+            sub_m.value_of("density").unwrap().parse()?,
+            sub_m.value_of("t60").unwrap().parse()?,
+            sub_m.value_of("seed").unwrap().parse()?,
+        )),
         _ => unreachable!(),
     };
 
-    let samples: Vec<_> = match reader.spec().sample_format {
-        SampleFormat::Int => match reader.spec().bits_per_sample {
-            16 => reader
-                .samples::<i16>()
-                .collect::<Result<Vec<_>, _>>()?
-                .iter()
-                .map(|s| f32::from(*s) / f32::from(std::i16::MAX))
-                .collect(),
-            32 => reader
-                .samples::<i32>()
-                .collect::<Result<Vec<_>, _>>()?
-                .iter()
-                .map(|s| *s as f32 / (std::i32::MAX as f32))
-                .collect(),
-            _ => unimplemented!(),
-        },
-        SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
-    };
-    let stereo_samples: Vec<_> = match input_channels {
-        1 => samples.iter().map(|s| (s, s)).collect(),
-        2 => samples
-            .iter()
-            .chunks(2)
-            .into_iter()
-            .map(|mut c| c.next_tuple().unwrap())
-            .collect(),
+    let interleaved_samples: Vec<_> = match input_channels {
+        1 => samples.iter().flat_map(|s| [f64::from(*s); 2]).collect(),
+        2 => samples.iter().map(|s| f64::from(*s)).collect(),
         _ => unimplemented!(),
     };
 
@@ -153,10 +340,15 @@ fn main() -> Result<()> {
     };
     let mut writer = WavWriter::create(output, write_spec)?;
 
-    for (l_in, r_in) in stereo_samples {
-        let (l_out, r_out) = reverb.process_sample((f64::from(*l_in), f64::from(*r_in)));
-        writer.write_sample((gain * f64::from(std::i16::MAX) * l_out) as i16)?;
-        writer.write_sample((gain * f64::from(std::i16::MAX) * r_out) as i16)?;
+    let mut output_samples = vec![0.0; interleaved_samples.len()];
+    {
+        // nothing but the processing runs with subnormals flushed
+        let _guard = unsafe { DenormalGuard::new() };
+        reverb.process_interleaved_block(&interleaved_samples, &mut output_samples);
+    }
+
+    for s in output_samples {
+        writer.write_sample((gain * f64::from(i16::MAX) * s) as i16)?;
     }
 
     writer.finalize()?;
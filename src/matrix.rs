@@ -0,0 +1,260 @@
+use crate::fft::{Complex, Fft};
+use crate::rng::Rng;
+use crate::sample::Sample;
+use std::f64::consts::PI;
+
+// lossless (orthogonal) matrices for mixing the outputs of delay lines
+// https://ccrma.stanford.edu/~jos/pasp/Choice_Lossless_Feedback_Matrix.html
+pub trait FeedbackMatrix<S: Sample = f64> {
+    fn size(&self) -> usize;
+
+    /// Multiplies `x` by the matrix in place. `x` must have `size()` elements
+    fn apply(&mut self, x: &mut [S]);
+}
+
+impl<S: Sample, M: FeedbackMatrix<S> + ?Sized> FeedbackMatrix<S> for Box<M> {
+    fn size(&self) -> usize {
+        (**self).size()
+    }
+
+    fn apply(&mut self, x: &mut [S]) {
+        (**self).apply(x)
+    }
+}
+
+// normalized so that it is orthogonal
+// https://ccrma.stanford.edu/~jos/pasp/Hadamard_Matrix.html
+pub struct Hadamard<S = f64> {
+    size: usize,
+    scale: S,
+}
+
+impl<S: Sample> Hadamard<S> {
+    /// `size` must be a power of two
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two());
+        Self {
+            size,
+            scale: S::from_f64(1.0 / (size as f64).sqrt()),
+        }
+    }
+}
+
+impl<S: Sample> FeedbackMatrix<S> for Hadamard<S> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    // fast Walsh-Hadamard transform
+    fn apply(&mut self, x: &mut [S]) {
+        assert_eq!(x.len(), self.size);
+        let mut len = 1;
+        while len < self.size {
+            for start in (0..self.size).step_by(2 * len) {
+                for i in start..start + len {
+                    let (a, b) = (x[i], x[i + len]);
+                    x[i] = a + b;
+                    x[i + len] = a - b;
+                }
+            }
+            len *= 2;
+        }
+        for x in x.iter_mut() {
+            *x *= self.scale;
+        }
+    }
+}
+
+// I - 2/N * 1 * 1^T
+// https://ccrma.stanford.edu/~jos/pasp/Householder_Feedback_Matrix.html
+pub struct Householder<S = f64> {
+    size: usize,
+    scale: S,
+}
+
+impl<S: Sample> Householder<S> {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            scale: S::from_f64(2.0 / size as f64),
+        }
+    }
+}
+
+impl<S: Sample> FeedbackMatrix<S> for Householder<S> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn apply(&mut self, x: &mut [S]) {
+        assert_eq!(x.len(), self.size);
+        let sum = self.scale * x.iter().copied().sum();
+        for x in x.iter_mut() {
+            *x -= sum;
+        }
+    }
+}
+
+// row-major dense matrix
+pub struct RandomOrthogonal<S = f64> {
+    size: usize,
+    elements: Vec<S>,
+    scratch: Vec<S>,
+}
+
+impl<S: Sample> RandomOrthogonal<S> {
+    /// Orthonormalizes a random Gaussian matrix generated from `seed`
+    pub fn new(size: usize, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut rows: Vec<Vec<f64>> = (0..size)
+            .map(|_| (0..size).map(|_| gaussian(&mut rng)).collect())
+            .collect();
+
+        // modified Gram-Schmidt process
+        for i in 0..size {
+            for j in 0..i {
+                let dot: f64 = rows[i].iter().zip(&rows[j]).map(|(a, b)| a * b).sum();
+                let (done, rest) = rows.split_at_mut(i);
+                for (a, b) in rest[0].iter_mut().zip(&done[j]) {
+                    *a -= dot * b;
+                }
+            }
+            let norm = rows[i].iter().map(|a| a * a).sum::<f64>().sqrt();
+            rows[i].iter_mut().for_each(|a| *a /= norm);
+        }
+
+        Self {
+            size,
+            elements: rows.iter().flatten().map(|a| S::from_f64(*a)).collect(),
+            scratch: vec![S::ZERO; size],
+        }
+    }
+}
+
+impl<S: Sample> FeedbackMatrix<S> for RandomOrthogonal<S> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn apply(&mut self, x: &mut [S]) {
+        assert_eq!(x.len(), self.size);
+        self.scratch.copy_from_slice(x);
+        for (y, row) in x.iter_mut().zip(self.elements.chunks_exact(self.size)) {
+            *y = row.iter().zip(&self.scratch).map(|(a, b)| *a * *b).sum();
+        }
+    }
+}
+
+// each row is the previous one rotated right by one element
+// https://ccrma.stanford.edu/~jos/pasp/Circulant_Feedback_Matrix.html
+// applied as a circular correlation with `first_row` through the FFT if the size is a power of two
+pub struct Circulant<S = f64> {
+    first_row: Vec<S>,
+    // FFT and the conjugated spectrum of `first_row` divided by the size
+    fft: Option<(Fft, Vec<Complex>)>,
+    buf: Vec<Complex>,
+    scratch: Vec<S>,
+}
+
+impl<S: Sample> Circulant<S> {
+    /// The matrix is lossless only if the DFT of `first_row` has unit magnitude
+    pub fn new(first_row: &[f64]) -> Self {
+        let size = first_row.len();
+        let fft = if size.is_power_of_two() {
+            let fft = Fft::new(size);
+            let mut spectrum: Vec<_> = first_row.iter().map(|a| Complex::new(*a, 0.0)).collect();
+            fft.forward(&mut spectrum);
+            let scale = 1.0 / size as f64;
+            for c in spectrum.iter_mut() {
+                *c = Complex::new(scale * c.re, -scale * c.im);
+            }
+            Some((fft, spectrum))
+        } else {
+            None
+        };
+        Self {
+            first_row: first_row.iter().map(|a| S::from_f64(*a)).collect(),
+            fft,
+            buf: vec![Complex::new(0.0, 0.0); size],
+            scratch: vec![S::ZERO; size],
+        }
+    }
+
+    /// Creates a lossless matrix whose eigenvalues have phases generated from `seed`
+    pub fn random(size: usize, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+
+        // the spectrum must be conjugate symmetric for the first row to be real
+        let mut phases = vec![0.0; size];
+        for k in 0..=size / 2 {
+            phases[k] = if k == 0 || 2 * k == size {
+                if rng.next_f64() < 0.5 {
+                    0.0
+                } else {
+                    PI
+                }
+            } else {
+                2.0 * PI * rng.next_f64()
+            };
+            if k > 0 && k < size - k {
+                phases[size - k] = -phases[k];
+            }
+        }
+
+        // inverse DFT
+        let first_row: Vec<_> = (0..size)
+            .map(|n| {
+                phases
+                    .iter()
+                    .enumerate()
+                    .map(|(k, phase)| (phase + 2.0 * PI * (k * n) as f64 / size as f64).cos())
+                    .sum::<f64>()
+                    / size as f64
+            })
+            .collect();
+        Self::new(&first_row)
+    }
+}
+
+impl<S: Sample> FeedbackMatrix<S> for Circulant<S> {
+    fn size(&self) -> usize {
+        self.first_row.len()
+    }
+
+    fn apply(&mut self, x: &mut [S]) {
+        let size = self.size();
+        assert_eq!(x.len(), size);
+        match &self.fft {
+            Some((fft, spectrum)) => {
+                for (b, x) in self.buf.iter_mut().zip(x.iter()) {
+                    *b = Complex::new(x.to_f64(), 0.0);
+                }
+                fft.forward(&mut self.buf);
+                for (b, c) in self.buf.iter_mut().zip(spectrum) {
+                    *b = *b * *c;
+                }
+                fft.inverse(&mut self.buf);
+                for (y, b) in x.iter_mut().zip(&self.buf) {
+                    *y = S::from_f64(b.re);
+                }
+            }
+            None => {
+                // y_i = sum_j first_row[j - i] * x_j, where j - i wraps around at j = i
+                self.scratch.copy_from_slice(x);
+                for (i, y) in x.iter_mut().enumerate() {
+                    let (before, after) = self.scratch.split_at(i);
+                    let (wrapped, row) = self.first_row.split_at(size - i);
+                    *y = row.iter().zip(before).map(|(a, b)| *a * *b).sum::<S>()
+                        + wrapped.iter().zip(after).map(|(a, b)| *a * *b).sum::<S>();
+                }
+            }
+        }
+    }
+}
+
+// Box-Muller transform
+fn gaussian(rng: &mut Rng) -> f64 {
+    let u1 = 1.0 - rng.next_f64();
+    let u2 = rng.next_f64();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
@@ -1,17 +1,159 @@
+mod convolution;
+mod dattorro;
+mod ducking;
+mod fdn;
 mod freeverb;
+mod gardner;
 mod jcrev;
+mod moorer;
 mod nrev;
+mod oversampling;
 mod prcrev;
 mod satrev;
 mod stk_jcrev;
+mod tone;
+mod velvet;
+mod zita_rev1;
 
+pub use convolution::Convolution;
+pub use dattorro::Dattorro;
+pub use ducking::Ducking;
+pub use fdn::{default_fdn_delays, FDN};
 pub use freeverb::Freeverb;
+pub use gardner::{Gardner, GardnerRoom};
 pub use jcrev::JCRev;
+pub use moorer::Moorer;
 pub use nrev::NRev;
+pub use oversampling::Oversampled;
 pub use prcrev::PRCRev;
 pub use satrev::SATREV;
 pub use stk_jcrev::STKJCRev;
+pub use tone::ToneControl;
+pub use velvet::VelvetNoise;
+pub use zita_rev1::ZitaRev1;
 
-pub trait Reverb {
-    fn process_sample(&mut self, x: (f64, f64)) -> (f64, f64);
+use crate::delay::{Delay, DelayLine};
+use crate::filter::Filter;
+use crate::sample::Sample;
+
+// block processing is done in sub-blocks of this size so that
+// intermediate buffers can be allocated on the stack
+const SUB_BLOCK_SIZE: usize = 64;
+
+// delays are preallocated so that `set_size` can scale them up to this factor
+const MAX_SIZE: f64 = 2.0;
+
+pub trait Reverb<S: Sample = f64> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S);
+
+    /// Clears the internal state as if no sample has been processed
+    fn reset(&mut self);
+
+    /// Part of the output for the input `x` that is `x` mixed in directly without any delay.
+    /// Reverbs without such a dry path output zeros
+    fn dry_output(&self, _x: (S, S)) -> (S, S) {
+        (S::ZERO, S::ZERO)
+    }
+
+    /// Processes planar stereo buffers, all of which must have the same length
+    fn process_block(&mut self, input: (&[S], &[S]), output: (&mut [S], &mut [S])) {
+        for_each_sub_block(input, output, |input, output| {
+            for (i, x) in input.0.iter().zip(input.1).enumerate() {
+                let y = self.process_sample((*x.0, *x.1));
+                output.0[i] = y.0;
+                output.1[i] = y.1;
+            }
+        });
+    }
+
+    /// Processes interleaved stereo buffers of the same even length
+    fn process_interleaved_block(&mut self, input: &[S], output: &mut [S]) {
+        assert_eq!(input.len(), output.len());
+        assert_eq!(input.len() % 2, 0);
+        for (input, output) in input
+            .chunks(2 * SUB_BLOCK_SIZE)
+            .zip(output.chunks_mut(2 * SUB_BLOCK_SIZE))
+        {
+            let len = input.len() / 2;
+            let mut planar_input = [[S::ZERO; SUB_BLOCK_SIZE]; 2];
+            let mut planar_output = [[S::ZERO; SUB_BLOCK_SIZE]; 2];
+            for (i, x) in input.chunks_exact(2).enumerate() {
+                planar_input[0][i] = x[0];
+                planar_input[1][i] = x[1];
+            }
+            let (l_out, r_out) = planar_output.split_at_mut(1);
+            self.process_block(
+                (&planar_input[0][..len], &planar_input[1][..len]),
+                (&mut l_out[0][..len], &mut r_out[0][..len]),
+            );
+            for (i, y) in output.chunks_exact_mut(2).enumerate() {
+                y[0] = planar_output[0][i];
+                y[1] = planar_output[1][i];
+            }
+        }
+    }
+}
+
+fn for_each_sub_block<S, F>(input: (&[S], &[S]), output: (&mut [S], &mut [S]), mut f: F)
+where
+    F: FnMut((&[S], &[S]), (&mut [S], &mut [S])),
+{
+    assert!(input.0.len() == input.1.len());
+    assert!(input.0.len() == output.0.len() && input.0.len() == output.1.len());
+    for (((l_in, r_in), l_out), r_out) in input
+        .0
+        .chunks(SUB_BLOCK_SIZE)
+        .zip(input.1.chunks(SUB_BLOCK_SIZE))
+        .zip(output.0.chunks_mut(SUB_BLOCK_SIZE))
+        .zip(output.1.chunks_mut(SUB_BLOCK_SIZE))
+    {
+        f((l_in, r_in), (l_out, r_out));
+    }
+}
+
+// averages both channels into the beginning of `buf` and returns that part
+fn downmix<'a, S: Sample>(input: (&[S], &[S]), buf: &'a mut [S; SUB_BLOCK_SIZE]) -> &'a mut [S] {
+    let buf = &mut buf[..input.0.len()];
+    for (y, x) in buf.iter_mut().zip(input.0.iter().zip(input.1)) {
+        *y = (*x.0 + *x.1) / S::from_f64(2.0);
+    }
+    buf
+}
+
+// rounded to whole samples and bypassed when that is zero,
+// as a delay line is always at least one sample long
+struct Predelay<S> {
+    delay: Option<Delay<S>>,
+}
+
+impl<S: Sample> Predelay<S> {
+    fn new(sample_rate: u32, ms: f64) -> Self {
+        let length = (f64::from(sample_rate) * ms / 1000.0).round();
+        Self {
+            delay: if length >= 1.0 {
+                Some(Delay::new(length as usize))
+            } else {
+                None
+            },
+        }
+    }
+}
+
+impl<S: Sample> Filter<S> for Predelay<S> {
+    fn process_sample(&mut self, x: S) -> S {
+        match &mut self.delay {
+            Some(delay) => {
+                let y = delay.output();
+                delay.input(x);
+                y
+            }
+            None => x,
+        }
+    }
+
+    fn reset(&mut self) {
+        if let Some(delay) = &mut self.delay {
+            delay.reset();
+        }
+    }
 }
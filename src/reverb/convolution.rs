@@ -0,0 +1,240 @@
+use super::oversampling::bessel_i0;
+use super::Reverb;
+use crate::fft::{Complex, Fft};
+use crate::sample::Sample;
+use crate::wav;
+use std::f64::consts::PI;
+use std::marker::PhantomData;
+use std::path::Path;
+
+// length of the partitions, which is also the latency
+const BLOCK_SIZE: usize = 256;
+
+// uniformly partitioned overlap-save convolution with a frequency-domain delay line
+// https://ccrma.stanford.edu/~jos/sasp/Overlap_Add_OLA_STFT_Processing.html
+pub struct Convolution<S = f64> {
+    fft: Fft,
+    // (input channel, output channel, spectra of the partitions of the impulse response)
+    paths: Vec<(usize, usize, Vec<Vec<Complex>>)>,
+    // spectra of the past input blocks of each channel, the latest one at `latest`
+    history: [Vec<Vec<Complex>>; 2],
+    latest: usize,
+    // previous and current input blocks
+    input: [Vec<f64>; 2],
+    output: [Vec<f64>; 2],
+    pos: usize,
+    buf: Vec<Complex>,
+    spectra: [Vec<Complex>; 2],
+    phantom: PhantomData<S>,
+}
+
+impl<S: Sample> Convolution<S> {
+    /// `ir` has 1 (mono), 2 (stereo) or 4 (true stereo, in the order of L to L, L to R,
+    /// R to L and R to R) channels, and is resampled if `ir_sample_rate` differs
+    pub fn new(ir: &[Vec<f64>], ir_sample_rate: u32, sample_rate: u32) -> Self {
+        let ir: Vec<_> = ir
+            .iter()
+            .map(|h| {
+                if ir_sample_rate == sample_rate {
+                    h.clone()
+                } else {
+                    resample(h, ir_sample_rate, sample_rate)
+                }
+            })
+            .collect();
+        let routing: &[(usize, usize, usize)] = match ir.len() {
+            1 => &[(0, 0, 0), (1, 1, 0)],
+            2 => &[(0, 0, 0), (1, 1, 1)],
+            4 => &[(0, 0, 0), (0, 1, 1), (1, 0, 2), (1, 1, 3)],
+            _ => panic!("impulse response must have 1, 2 or 4 channels"),
+        };
+
+        let fft = Fft::new(2 * BLOCK_SIZE);
+        let ir_len = ir.iter().map(Vec::len).max().unwrap_or(0);
+        let partitions = ir_len.div_ceil(BLOCK_SIZE).max(1);
+        let spectra = |h: &[f64]| -> Vec<Vec<Complex>> {
+            (0..partitions)
+                .map(|p| {
+                    // scaled to compensate for the unnormalized inverse FFT
+                    let mut buf = vec![Complex::default(); 2 * BLOCK_SIZE];
+                    for (y, x) in buf
+                        .iter_mut()
+                        .zip(h.iter().skip(p * BLOCK_SIZE).take(BLOCK_SIZE))
+                    {
+                        *y = Complex::new(x / (2 * BLOCK_SIZE) as f64, 0.0);
+                    }
+                    fft.forward(&mut buf);
+                    buf.truncate(BLOCK_SIZE + 1);
+                    buf
+                })
+                .collect()
+        };
+        let paths = routing
+            .iter()
+            .map(|(from, to, channel)| (*from, *to, spectra(&ir[*channel])))
+            .collect();
+
+        let history = || vec![vec![Complex::default(); BLOCK_SIZE + 1]; partitions];
+        Self {
+            fft,
+            paths,
+            history: [history(), history()],
+            latest: 0,
+            input: [vec![0.0; 2 * BLOCK_SIZE], vec![0.0; 2 * BLOCK_SIZE]],
+            output: [vec![0.0; BLOCK_SIZE], vec![0.0; BLOCK_SIZE]],
+            pos: 0,
+            buf: vec![Complex::default(); 2 * BLOCK_SIZE],
+            spectra: [
+                vec![Complex::default(); BLOCK_SIZE + 1],
+                vec![Complex::default(); BLOCK_SIZE + 1],
+            ],
+            phantom: PhantomData,
+        }
+    }
+
+    /// Loads an impulse response in the channel layouts accepted by `new`
+    pub fn from_wav<P: AsRef<Path>>(path: P, sample_rate: u32) -> hound::Result<Self> {
+        let (spec, samples) = wav::read(path)?;
+        let channels = usize::from(spec.channels);
+        if channels != 1 && channels != 2 && channels != 4 {
+            return Err(hound::Error::Unsupported);
+        }
+        let ir: Vec<Vec<_>> = (0..channels)
+            .map(|c| {
+                samples
+                    .iter()
+                    .skip(c)
+                    .step_by(channels)
+                    .map(|s| f64::from(*s))
+                    .collect()
+            })
+            .collect();
+        Ok(Self::new(&ir, spec.sample_rate, sample_rate))
+    }
+
+    /// Delay of the output in samples
+    pub fn latency(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn convolve(&mut self) {
+        let n = 2 * BLOCK_SIZE;
+
+        // both channels are transformed at once as the real and the imaginary parts
+        for (y, (l, r)) in self
+            .buf
+            .iter_mut()
+            .zip(self.input[0].iter().zip(&self.input[1]))
+        {
+            *y = Complex::new(*l, *r);
+        }
+        self.fft.forward(&mut self.buf);
+
+        let partitions = self.history[0].len();
+        self.latest = (self.latest + partitions - 1) % partitions;
+        for k in 0..=BLOCK_SIZE {
+            let a = self.buf[k];
+            let b = self.buf[(n - k) % n].conj();
+            self.history[0][self.latest][k] = Complex::new(0.5, 0.0) * (a + b);
+            self.history[1][self.latest][k] = Complex::new(0.0, -0.5) * (a - b);
+        }
+
+        for spectrum in self.spectra.iter_mut() {
+            spectrum.iter_mut().for_each(|y| *y = Complex::default());
+        }
+        for (from, to, h) in self.paths.iter() {
+            for (p, h) in h.iter().enumerate() {
+                let x = &self.history[*from][(self.latest + p) % partitions];
+                for (y, (h, x)) in self.spectra[*to].iter_mut().zip(h.iter().zip(x)) {
+                    *y = *y + *h * *x;
+                }
+            }
+        }
+
+        // the outputs are real, so they are packed back in the same way
+        let j = Complex::new(0.0, 1.0);
+        for (k, y) in self.buf.iter_mut().enumerate() {
+            *y = if k <= BLOCK_SIZE {
+                self.spectra[0][k] + j * self.spectra[1][k]
+            } else {
+                self.spectra[0][n - k].conj() + j * self.spectra[1][n - k].conj()
+            };
+        }
+        self.fft.inverse(&mut self.buf);
+
+        // only the latter half is free from circular aliasing
+        for (i, y) in self.buf[BLOCK_SIZE..].iter().enumerate() {
+            self.output[0][i] = y.re;
+            self.output[1][i] = y.im;
+        }
+        for input in self.input.iter_mut() {
+            input.copy_within(BLOCK_SIZE.., 0);
+        }
+    }
+}
+
+impl<S: Sample> Reverb<S> for Convolution<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        self.input[0][BLOCK_SIZE + self.pos] = x.0.to_f64();
+        self.input[1][BLOCK_SIZE + self.pos] = x.1.to_f64();
+        let y = (
+            S::from_f64(self.output[0][self.pos]),
+            S::from_f64(self.output[1][self.pos]),
+        );
+        self.pos += 1;
+        if self.pos == BLOCK_SIZE {
+            self.convolve();
+            self.pos = 0;
+        }
+        y
+    }
+
+    fn reset(&mut self) {
+        for history in self.history.iter_mut() {
+            for x in history.iter_mut() {
+                x.iter_mut().for_each(|x| *x = Complex::default());
+            }
+        }
+        for x in self.input.iter_mut().chain(self.output.iter_mut()) {
+            x.iter_mut().for_each(|x| *x = 0.0);
+        }
+        self.pos = 0;
+    }
+}
+
+// bandlimited interpolation with a Kaiser-windowed sinc, scaled so that
+// the frequency response of the impulse response keeps its gain
+// https://ccrma.stanford.edu/~jos/resample/
+fn resample(x: &[f64], from: u32, to: u32) -> Vec<f64> {
+    const ZERO_CROSSINGS: f64 = 32.0;
+    const BETA: f64 = 8.0;
+    if x.is_empty() {
+        return Vec::new();
+    }
+    let ratio = f64::from(to) / f64::from(from);
+    // lowpasses below the new Nyquist frequency when downsampling
+    let cutoff = ratio.min(1.0);
+    let half_width = ZERO_CROSSINGS / cutoff;
+    let gain = cutoff / ratio / bessel_i0(BETA);
+    let len = (x.len() as f64 * ratio).ceil() as usize;
+    (0..len)
+        .map(|n| {
+            let t = n as f64 / ratio;
+            let start = (t - half_width).ceil().max(0.0) as usize;
+            let end = ((t + half_width).floor() as usize).min(x.len() - 1);
+            (start..=end)
+                .map(|i| {
+                    let d = t - i as f64;
+                    let r = d / half_width;
+                    let window = bessel_i0(BETA * (1.0 - r * r).max(0.0).sqrt());
+                    let sinc = if d == 0.0 {
+                        1.0
+                    } else {
+                        (PI * cutoff * d).sin() / (PI * cutoff * d)
+                    };
+                    gain * x[i] * sinc * window
+                })
+                .sum()
+        })
+        .collect()
+}
@@ -0,0 +1,156 @@
+use super::{Predelay, Reverb};
+use crate::delay::{Delay, DelayLine, Interpolation, ModulatedDelay};
+use crate::filter::{Allpass, Filter, OnePoleLowpass};
+use crate::lfo::{Lfo, Waveform};
+use crate::sample::Sample;
+
+// Dattorro, J. (1997). Effect Design Part 1: Reverberator and Other Filters.
+// delay lengths are given in samples at this rate
+const REFERENCE_SAMPLE_RATE: f64 = 29761.0;
+
+pub struct Dattorro<S = f64> {
+    predelay: Predelay<S>,
+    bandwidth: OnePoleLowpass<S>,
+    diffusers: [Allpass<S>; 4],
+    tank: [TankHalf<S>; 2],
+    decay: S,
+    // (position, gain) of the output taps on the left and the right tank halves
+    taps: [[(f64, f64); 7]; 2],
+}
+
+impl<S: Sample> Dattorro<S> {
+    /// `bandwidth` and `damping` are the coefficients of the input and the tank lowpasses,
+    /// `decay` is the gain of the tank in [0, 1)
+    pub fn new(
+        sample_rate: u32,
+        predelay_ms: f64,
+        bandwidth: f64,
+        damping: f64,
+        decay: f64,
+    ) -> Self {
+        let scale = |d| f64::from(sample_rate) / REFERENCE_SAMPLE_RATE * f64::from(d);
+        let scale_delay = |d| Delay::fractional(scale(d), Interpolation::Allpass);
+        let decay_diffusion2 = (decay + 0.15).clamp(0.25, 0.5);
+        let tank_half = |modulated, delay1, delay2, allpass2, phase| TankHalf {
+            // the allpass coefficient is negated as in the paper
+            modulated: Allpass::new(
+                0.7,
+                0.7,
+                ModulatedDelay::new(
+                    scale(modulated),
+                    scale(8),
+                    Lfo::new(sample_rate, Waveform::Sine, 1.0, phase),
+                    Interpolation::Lagrange,
+                ),
+            ),
+            delay1: scale_delay(delay1),
+            damping: OnePoleLowpass::from_pole(damping),
+            allpass: Allpass::new(-decay_diffusion2, -decay_diffusion2, scale_delay(allpass2)),
+            delay2: scale_delay(delay2),
+        };
+        // each output reads the delays of the node names in the paper, in the order of
+        // 48_54, 48_54, 55_59, 59_63, 24_30, 31_33, 33_39 and
+        // 24_30, 24_30, 31_33, 33_39, 48_54, 55_59, 59_63 respectively
+        let taps = |positions: [u32; 7]| {
+            let gains = [0.6, 0.6, -0.6, 0.6, -0.6, -0.6, -0.6];
+            let mut taps = [(0.0, 0.0); 7];
+            for (tap, (position, gain)) in taps.iter_mut().zip(positions.iter().zip(&gains)) {
+                *tap = (scale(*position), *gain);
+            }
+            taps
+        };
+        Self {
+            predelay: Predelay::new(sample_rate, predelay_ms),
+            bandwidth: OnePoleLowpass::from_pole(1.0 - bandwidth),
+            diffusers: [
+                Allpass::new(-0.75, -0.75, scale_delay(142)),
+                Allpass::new(-0.75, -0.75, scale_delay(107)),
+                Allpass::new(-0.625, -0.625, scale_delay(379)),
+                Allpass::new(-0.625, -0.625, scale_delay(277)),
+            ],
+            tank: [
+                tank_half(672, 4453, 3720, 1800, 0.0),
+                tank_half(908, 4217, 3163, 2656, 0.25),
+            ],
+            decay: S::from_f64(decay),
+            taps: [
+                taps([266, 2974, 1913, 1996, 1990, 187, 1066]),
+                taps([353, 3627, 1228, 2673, 2111, 335, 121]),
+            ],
+        }
+    }
+
+    fn process_mono(&mut self, x: S) -> (S, S) {
+        let x = self.predelay.process_sample(x);
+        let x = self.bandwidth.process_sample(x);
+        let x = self
+            .diffusers
+            .iter_mut()
+            .fold(x, |acc, a| a.process_sample(acc));
+
+        let output = (self.tap_output(0), self.tap_output(1));
+
+        // each half is fed by the other
+        let feedback = (self.tank[1].delay2.output(), self.tank[0].delay2.output());
+        let decay = self.decay;
+        self.tank[0].process_sample(x + decay * feedback.0, decay);
+        self.tank[1].process_sample(x + decay * feedback.1, decay);
+
+        output
+    }
+
+    // taps the opposite half first, then the same half
+    fn tap_output(&self, channel: usize) -> S {
+        let [a, b, c, d, e, f, g] = self.taps[channel];
+        let (opposite, same) = (&self.tank[1 - channel], &self.tank[channel]);
+        let tap = |delay: &Delay<S>, (position, gain): (f64, f64)| {
+            S::from_f64(gain) * delay.tap_output(position)
+        };
+        tap(&opposite.delay1, a)
+            + tap(&opposite.delay1, b)
+            + tap(opposite.allpass.delay(), c)
+            + tap(&opposite.delay2, d)
+            + tap(&same.delay1, e)
+            + tap(same.allpass.delay(), f)
+            + tap(&same.delay2, g)
+    }
+}
+
+impl<S: Sample> Reverb<S> for Dattorro<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        self.process_mono((x.0 + x.1) / S::from_f64(2.0))
+    }
+
+    fn reset(&mut self) {
+        self.predelay.reset();
+        self.bandwidth.reset();
+        self.diffusers.iter_mut().for_each(Filter::reset);
+        for half in self.tank.iter_mut() {
+            half.modulated.reset();
+            half.delay1.reset();
+            half.damping.reset();
+            half.allpass.reset();
+            half.delay2.reset();
+        }
+    }
+}
+
+struct TankHalf<S> {
+    modulated: Allpass<S, ModulatedDelay<S>>,
+    delay1: Delay<S>,
+    damping: OnePoleLowpass<S>,
+    allpass: Allpass<S>,
+    delay2: Delay<S>,
+}
+
+impl<S: Sample> TankHalf<S> {
+    fn process_sample(&mut self, x: S, decay: S) {
+        let y = self.modulated.process_sample(x);
+        let delayed = self.delay1.output();
+        self.delay1.input(y);
+        let y = self
+            .allpass
+            .process_sample(decay * self.damping.process_sample(delayed));
+        self.delay2.input(y);
+    }
+}
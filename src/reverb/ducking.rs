@@ -0,0 +1,93 @@
+use super::{for_each_sub_block, Reverb, SUB_BLOCK_SIZE};
+use crate::dynamics::EnvelopeFollower;
+use crate::filter::Filter;
+use crate::sample::Sample;
+
+/// Attenuates the wet output of any reverb while the input is loud, so that the tail
+/// ducks under the dry signal. The part given by `Reverb::dry_output` passes through unchanged
+pub struct Ducking<R, S = f64> {
+    reverb: R,
+    follower: EnvelopeFollower<S>,
+    threshold_db: f64,
+    ratio: f64,
+}
+
+impl<R: Reverb<S>, S: Sample> Ducking<R, S> {
+    /// Above `threshold_db`, the output is attenuated so that every `ratio` dB of the input
+    /// envelope results in 1 dB. The attenuation is held for `hold_ms` after the input falls
+    pub fn new(
+        reverb: R,
+        mut follower: EnvelopeFollower<S>,
+        threshold_db: f64,
+        ratio: f64,
+        hold_ms: f64,
+    ) -> Self {
+        follower.set_hold(hold_ms);
+        Self {
+            reverb,
+            follower,
+            threshold_db,
+            ratio: ratio.max(1.0),
+        }
+    }
+
+    pub fn reverb(&self) -> &R {
+        &self.reverb
+    }
+
+    pub fn reverb_mut(&mut self) -> &mut R {
+        &mut self.reverb
+    }
+
+    // follows the louder channel so that the detection does not depend on panning
+    fn gain(&mut self, x: (S, S)) -> S {
+        let louder = if x.0.to_f64().abs() > x.1.to_f64().abs() {
+            x.0
+        } else {
+            x.1
+        };
+        let envelope = self.follower.process_sample(louder).to_f64();
+        let over = 20.0 * envelope.log10() - self.threshold_db;
+        if over > 0.0 {
+            S::from_f64(f64::powf(10.0, -over * (1.0 - 1.0 / self.ratio) / 20.0))
+        } else {
+            S::from_f64(1.0)
+        }
+    }
+}
+
+impl<R: Reverb<S>, S: Sample> Reverb<S> for Ducking<R, S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let gain = self.gain(x);
+        let y = self.reverb.process_sample(x);
+        let dry = self.reverb.dry_output(x);
+        (dry.0 + gain * (y.0 - dry.0), dry.1 + gain * (y.1 - dry.1))
+    }
+
+    fn process_block(&mut self, input: (&[S], &[S]), output: (&mut [S], &mut [S])) {
+        for_each_sub_block(input, output, |x, output| {
+            let mut gains = [S::ZERO; SUB_BLOCK_SIZE];
+            for (g, x) in gains.iter_mut().zip(x.0.iter().zip(x.1)) {
+                *g = self.gain((*x.0, *x.1));
+            }
+
+            self.reverb
+                .process_block(x, (&mut *output.0, &mut *output.1));
+
+            for (i, g) in gains.iter().take(x.0.len()).enumerate() {
+                let dry = self.reverb.dry_output((x.0[i], x.1[i]));
+                output.0[i] = dry.0 + *g * (output.0[i] - dry.0);
+                output.1[i] = dry.1 + *g * (output.1[i] - dry.1);
+            }
+        });
+    }
+
+    fn dry_output(&self, x: (S, S)) -> (S, S) {
+        self.reverb.dry_output(x)
+    }
+
+    fn reset(&mut self) {
+        self.reverb.reset();
+        self.follower.reset();
+    }
+}
@@ -0,0 +1,88 @@
+use super::Reverb;
+use crate::delay::{Delay, DelayLine, Interpolation};
+use crate::matrix::FeedbackMatrix;
+use crate::sample::Sample;
+
+// https://ccrma.stanford.edu/~jos/pasp/FDN_Reverberation.html
+// the left input and output are connected to the even lines and the right ones to the odd lines
+pub struct FDN<M, S = f64> {
+    delays: Vec<Delay<S>>,
+    gains: Vec<S>,
+    matrix: M,
+    output_gain: S,
+    scratch: Vec<S>,
+}
+
+impl<M: FeedbackMatrix<S>, S: Sample> FDN<M, S> {
+    /// `delays_ms` must have as many elements as the size of `matrix`, which must be even.
+    /// The gain of each line is set so that the tail decays by 60 dB in `t60` seconds
+    pub fn new(sample_rate: u32, matrix: M, delays_ms: &[f64], t60: f64) -> Self {
+        let order = matrix.size();
+        assert_eq!(delays_ms.len(), order);
+        assert!(order >= 2 && order % 2 == 0);
+        let delays: Vec<Delay<S>> = delays_ms
+            .iter()
+            .map(|ms| Delay::from_millis(sample_rate, *ms, Interpolation::Allpass))
+            .collect();
+        // -60 dB per t60 seconds, computed from the lengths at the actual sample rate
+        let gains = delays
+            .iter()
+            .map(|d| {
+                let seconds = d.length() / f64::from(sample_rate);
+                S::from_f64(f64::powf(10.0, -3.0 * seconds / t60))
+            })
+            .collect();
+        Self {
+            delays,
+            gains,
+            matrix,
+            output_gain: S::from_f64(1.0 / (order as f64 / 2.0).sqrt()),
+            scratch: vec![S::ZERO; order],
+        }
+    }
+}
+
+impl<M: FeedbackMatrix<S>, S: Sample> Reverb<S> for FDN<M, S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let mut output = (S::ZERO, S::ZERO);
+        for (i, (v, (delay, gain))) in self
+            .scratch
+            .iter_mut()
+            .zip(self.delays.iter().zip(&self.gains))
+            .enumerate()
+        {
+            let y = delay.output();
+            if i % 2 == 0 {
+                output.0 += y;
+            } else {
+                output.1 += y;
+            }
+            *v = *gain * y;
+        }
+
+        self.matrix.apply(&mut self.scratch);
+
+        for (i, (delay, v)) in self.delays.iter_mut().zip(&self.scratch).enumerate() {
+            delay.input(*v + if i % 2 == 0 { x.0 } else { x.1 });
+        }
+
+        (self.output_gain * output.0, self.output_gain * output.1)
+    }
+
+    fn reset(&mut self) {
+        self.delays.iter_mut().for_each(DelayLine::reset);
+    }
+}
+
+/// Delay lengths in milliseconds suitable for an FDN of order 4, 8 or 16, or `None` for other orders
+pub fn default_fdn_delays(order: usize) -> Option<&'static [f64]> {
+    match order {
+        4 => Some(&[29.7, 37.1, 41.1, 43.7]),
+        8 => Some(&[29.7, 31.3, 37.1, 41.1, 43.7, 47.9, 53.3, 59.3]),
+        16 => Some(&[
+            23.1, 25.3, 27.7, 29.7, 31.3, 35.3, 37.1, 41.1, 43.7, 47.9, 53.3, 57.7, 59.3, 64.7,
+            69.1, 73.3,
+        ]),
+        _ => None,
+    }
+}
@@ -1,33 +1,58 @@
-use super::Reverb;
+use super::{for_each_sub_block, Reverb, MAX_SIZE, SUB_BLOCK_SIZE};
+use crate::delay::{Delay, Interpolation, Transition};
 use crate::filter::{FeedbackComb, FeedforwardComb, Filter, LowpassFeedbackComb};
+use crate::sample::Sample;
 
 // https://ccrma.stanford.edu/~jos/pasp/Freeverb.html
 // http://freeverb3vst.osdn.jp/
-pub struct Freeverb {
-    monos: [MonoFreeverb; 2],
-    wet1: f64,
-    wet2: f64,
-    dry: f64,
+pub struct Freeverb<S = f64> {
+    monos: [MonoFreeverb<S>; 2],
+    wet1: S,
+    wet2: S,
+    dry: S,
 }
 
-impl Freeverb {
+impl<S: Sample> Freeverb<S> {
     pub fn new(sample_rate: u32, roomsize: f64, damp: f64, width: f64, wet: f64, dry: f64) -> Self {
-        let feedback = 0.28 * roomsize + 0.7;
+        let feedback = feedback_from_roomsize(roomsize);
         Self {
             monos: [
                 MonoFreeverb::new(sample_rate, feedback, damp, 0),
                 MonoFreeverb::new(sample_rate, feedback, damp, 23),
             ],
-            wet1: 1.5 * wet * (1.0 + width),
-            wet2: 1.5 * wet * (1.0 - width),
-            dry: 2.0 * dry,
+            wet1: S::from_f64(1.5 * wet * (1.0 + width)),
+            wet2: S::from_f64(1.5 * wet * (1.0 - width)),
+            dry: S::from_f64(2.0 * dry),
         }
     }
+
+    pub fn set_roomsize(&mut self, roomsize: f64) {
+        let feedback = feedback_from_roomsize(roomsize);
+        for mono in self.monos.iter_mut() {
+            for c in mono.lfbcs.iter_mut() {
+                c.set_feedback(feedback);
+            }
+        }
+    }
+
+    /// Scales the lengths of all delays, where 1 is the original size and 2 is the maximum
+    pub fn set_size(&mut self, size: f64, transition: Transition) {
+        for mono in self.monos.iter_mut() {
+            mono.set_size(size, transition);
+        }
+    }
+}
+
+fn feedback_from_roomsize(roomsize: f64) -> f64 {
+    0.28 * roomsize + 0.7
 }
 
-impl Reverb for Freeverb {
-    fn process_sample(&mut self, x: (f64, f64)) -> (f64, f64) {
-        let input = 0.015 * (x.0 + x.1);
+const COMB_DELAYS: [u32; 8] = [1557, 1617, 1491, 1422, 1277, 1356, 1188, 1116];
+const ALLPASS_DELAYS: [u32; 4] = [225, 556, 441, 341];
+
+impl<S: Sample> Reverb<S> for Freeverb<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let input = S::from_f64(0.015) * (x.0 + x.1);
         let out = (
             self.monos[0].process_sample(input),
             self.monos[1].process_sample(input),
@@ -37,38 +62,79 @@ impl Reverb for Freeverb {
             self.wet1 * out.1 + self.wet2 * out.0 + self.dry * x.1,
         )
     }
+
+    fn process_block(&mut self, input: (&[S], &[S]), output: (&mut [S], &mut [S])) {
+        for_each_sub_block(input, output, |x, output| {
+            let mut buf = [S::ZERO; SUB_BLOCK_SIZE];
+            let input = &mut buf[..x.0.len()];
+            for (y, x) in input.iter_mut().zip(x.0.iter().zip(x.1)) {
+                *y = S::from_f64(0.015) * (*x.0 + *x.1);
+            }
+
+            self.monos[0].process_block(input, output.0);
+            self.monos[1].process_block(input, output.1);
+
+            for (i, x) in x.0.iter().zip(x.1).enumerate() {
+                let out = (output.0[i], output.1[i]);
+                output.0[i] = self.wet1 * out.0 + self.wet2 * out.1 + self.dry * *x.0;
+                output.1[i] = self.wet1 * out.1 + self.wet2 * out.0 + self.dry * *x.1;
+            }
+        });
+    }
+
+    fn dry_output(&self, x: (S, S)) -> (S, S) {
+        (self.dry * x.0, self.dry * x.1)
+    }
+
+    fn reset(&mut self) {
+        self.monos.iter_mut().for_each(Filter::reset);
+    }
 }
 
-struct MonoFreeverb {
-    lfbcs: [LowpassFeedbackComb; 8],
-    allpasses: [(FeedbackComb, FeedforwardComb); 4],
+struct MonoFreeverb<S> {
+    lfbcs: [LowpassFeedbackComb<S>; 8],
+    allpasses: [(FeedbackComb<S>, FeedforwardComb<S>); 4],
+    sample_rate: u32,
+    stereo_spread: u32,
 }
 
-impl MonoFreeverb {
+impl<S: Sample> MonoFreeverb<S> {
     fn new(sample_rate: u32, feedback: f64, damp: f64, stereo_spread: u32) -> Self {
-        let scale_delay = |d| (f64::from(sample_rate) / 44100.0 * f64::from(d)) as usize;
-        macro_rules! lfbcs_from_delays {
-            ($($delay:expr),*) => {[$(
-                LowpassFeedbackComb::new(feedback, damp, scale_delay($delay + stereo_spread)),
-            )*]}
-        }
-        macro_rules! allpasses_from_delays {
-            ($($delay:expr),*) => {[$(
+        let scale = |d| scaled_length(sample_rate, d + stereo_spread, 1.0);
+        let scale_delay =
+            |d| Delay::with_max_length(scale(d), MAX_SIZE * scale(d), Interpolation::Allpass);
+        Self {
+            lfbcs: COMB_DELAYS.map(|d| LowpassFeedbackComb::new(feedback, damp, scale_delay(d))),
+            allpasses: ALLPASS_DELAYS.map(|d| {
                 (
-                    FeedbackComb::new(-0.5, scale_delay($delay + stereo_spread)),
-                    FeedforwardComb::new(-1.0, 1.5, scale_delay($delay + stereo_spread)),
-                ),
-            )*]}
+                    FeedbackComb::new(-0.5, scale_delay(d)),
+                    FeedforwardComb::new(-1.0, 1.5, scale_delay(d)),
+                )
+            }),
+            sample_rate,
+            stereo_spread,
         }
-        Self {
-            lfbcs: lfbcs_from_delays![1557, 1617, 1491, 1422, 1277, 1356, 1188, 1116],
-            allpasses: allpasses_from_delays![225, 556, 441, 341],
+    }
+
+    fn set_size(&mut self, size: f64, transition: Transition) {
+        let (sample_rate, stereo_spread) = (self.sample_rate, self.stereo_spread);
+        let scale = |d| scaled_length(sample_rate, d + stereo_spread, size);
+        for (c, d) in self.lfbcs.iter_mut().zip(&COMB_DELAYS) {
+            c.delay_mut().set_length(scale(*d), transition);
+        }
+        for ((fbcf, ffcf), d) in self.allpasses.iter_mut().zip(&ALLPASS_DELAYS) {
+            fbcf.delay_mut().set_length(scale(*d), transition);
+            ffcf.delay_mut().set_length(scale(*d), transition);
         }
     }
 }
 
-impl Filter for MonoFreeverb {
-    fn process_sample(&mut self, x: f64) -> f64 {
+fn scaled_length(sample_rate: u32, delay: u32, size: f64) -> f64 {
+    f64::from(sample_rate) / 44100.0 * f64::from(delay) * size
+}
+
+impl<S: Sample> Filter<S> for MonoFreeverb<S> {
+    fn process_sample(&mut self, x: S) -> S {
         let lfbc_output = self.lfbcs.iter_mut().map(|c| c.process_sample(x)).sum();
 
         self.allpasses
@@ -77,4 +143,26 @@ impl Filter for MonoFreeverb {
                 ffcf.process_sample(fbcf.process_sample(acc))
             })
     }
+
+    fn process_block(&mut self, input: &[S], output: &mut [S]) {
+        output.iter_mut().for_each(|y| *y = S::ZERO);
+        for c in self.lfbcs.iter_mut() {
+            for (y, x) in output.iter_mut().zip(input) {
+                *y += c.process_sample(*x);
+            }
+        }
+
+        for (fbcf, ffcf) in self.allpasses.iter_mut() {
+            fbcf.process_block_in_place(output);
+            ffcf.process_block_in_place(output);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.lfbcs.iter_mut().for_each(Filter::reset);
+        for (fbcf, ffcf) in self.allpasses.iter_mut() {
+            fbcf.reset();
+            ffcf.reset();
+        }
+    }
 }
@@ -0,0 +1,201 @@
+use super::Reverb;
+use crate::delay::{Delay, DelayLine, Interpolation};
+use crate::filter::{Allpass, Filter, NestedAllpass, OnePoleLowpass, Series};
+use crate::sample::Sample;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GardnerRoom {
+    Small,
+    Medium,
+    Large,
+}
+
+// Gardner, W. G. (1992). The virtual acoustic room.
+// nested allpasses in a loop closed through a lowpass, with the output taken at several points
+pub struct Gardner<S = f64> {
+    network: Network<S>,
+    lowpass: OnePoleLowpass<S>,
+    feedback_gain: S,
+    feedback: S,
+}
+
+type SingleNested<S> = NestedAllpass<Allpass<S>, S>;
+type DoubleNested<S> = NestedAllpass<Series<(Allpass<S>, Allpass<S>)>, S>;
+
+enum Network<S> {
+    Small {
+        double: DoubleNested<S>,
+        single: SingleNested<S>,
+    },
+    Medium {
+        double: DoubleNested<S>,
+        delay1: Delay<S>,
+        allpass: Allpass<S>,
+        delay2: Delay<S>,
+        delay3: Delay<S>,
+        single: SingleNested<S>,
+        delay4: Delay<S>,
+    },
+    Large {
+        allpasses: [Allpass<S>; 2],
+        delay1: Delay<S>,
+        double: DoubleNested<S>,
+        delay2: Delay<S>,
+        single: SingleNested<S>,
+        delay3: Delay<S>,
+    },
+}
+
+impl<S: Sample> Gardner<S> {
+    pub fn new(sample_rate: u32, room: GardnerRoom) -> Self {
+        let delay = |ms| Delay::from_millis(sample_rate, ms, Interpolation::Allpass);
+        let allpass = |g: f64, ms| Allpass::new(-g, -g, delay(ms));
+        let single =
+            |g, ms, inner: (f64, f64)| NestedAllpass::single(g, delay(ms), inner.0, delay(inner.1));
+        let double = |g, ms, inner1: (f64, f64), inner2: (f64, f64)| {
+            NestedAllpass::double(
+                g,
+                delay(ms),
+                (inner1.0, inner2.0),
+                (delay(inner1.1), delay(inner2.1)),
+            )
+        };
+        let (network, cutoff, feedback_gain) = match room {
+            GardnerRoom::Small => (
+                Network::Small {
+                    double: double(0.3, 35.0, (0.4, 22.0), (0.6, 8.3)),
+                    single: single(0.1, 66.0, (0.4, 30.0)),
+                },
+                4200.0,
+                0.5,
+            ),
+            GardnerRoom::Medium => (
+                Network::Medium {
+                    double: double(0.3, 35.0, (0.7, 8.3), (0.5, 22.0)),
+                    delay1: delay(5.0),
+                    allpass: allpass(0.5, 30.0),
+                    delay2: delay(67.0),
+                    delay3: delay(15.0),
+                    single: single(0.3, 39.0, (0.6, 9.8)),
+                    delay4: delay(108.0),
+                },
+                2500.0,
+                0.4,
+            ),
+            GardnerRoom::Large => (
+                Network::Large {
+                    allpasses: [allpass(0.3, 8.0), allpass(0.3, 12.0)],
+                    delay1: delay(4.0),
+                    double: double(0.5, 87.0, (0.25, 62.0), (0.25, 120.0)),
+                    delay2: delay(17.0),
+                    single: single(0.5, 76.0, (0.25, 30.0)),
+                    delay3: delay(31.0),
+                },
+                2600.0,
+                0.5,
+            ),
+        };
+        Self {
+            network,
+            lowpass: OnePoleLowpass::new(sample_rate, cutoff),
+            feedback_gain: S::from_f64(feedback_gain),
+            feedback: S::ZERO,
+        }
+    }
+
+    fn process_mono(&mut self, x: S) -> S {
+        let c = S::from_f64;
+        let x = x + self.feedback_gain * self.lowpass.process_sample(self.feedback);
+        let (output, feedback) = match &mut self.network {
+            Network::Small { double, single } => {
+                let a = double.process_sample(x);
+                let b = single.process_sample(a);
+                (c(0.5) * a + c(0.5) * b, b)
+            }
+            Network::Medium {
+                double,
+                delay1,
+                allpass,
+                delay2,
+                delay3,
+                single,
+                delay4,
+            } => {
+                let a = double.process_sample(x);
+                let b = delayed(delay2, allpass.process_sample(delayed(delay1, a)));
+                let d = single.process_sample(delayed(delay3, b));
+                (c(0.5) * a + c(0.5) * b + c(0.5) * d, delayed(delay4, d))
+            }
+            Network::Large {
+                allpasses,
+                delay1,
+                double,
+                delay2,
+                single,
+                delay3,
+            } => {
+                let a = allpasses.iter_mut().fold(x, |acc, a| a.process_sample(acc));
+                let b = double.process_sample(delayed(delay1, a));
+                let d = single.process_sample(delayed(delay2, b));
+                (c(0.34) * a + c(0.14) * b + c(0.14) * d, delayed(delay3, d))
+            }
+        };
+        self.feedback = feedback;
+        output
+    }
+}
+
+impl<S: Sample> Reverb<S> for Gardner<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let y = self.process_mono((x.0 + x.1) / S::from_f64(2.0));
+        (y, y)
+    }
+
+    fn reset(&mut self) {
+        match &mut self.network {
+            Network::Small { double, single } => {
+                double.reset();
+                single.reset();
+            }
+            Network::Medium {
+                double,
+                delay1,
+                allpass,
+                delay2,
+                delay3,
+                single,
+                delay4,
+            } => {
+                double.reset();
+                allpass.reset();
+                single.reset();
+                for d in [delay1, delay2, delay3, delay4] {
+                    d.reset();
+                }
+            }
+            Network::Large {
+                allpasses,
+                delay1,
+                double,
+                delay2,
+                single,
+                delay3,
+            } => {
+                allpasses.iter_mut().for_each(Filter::reset);
+                double.reset();
+                single.reset();
+                for d in [delay1, delay2, delay3] {
+                    d.reset();
+                }
+            }
+        }
+        self.lowpass.reset();
+        self.feedback = S::ZERO;
+    }
+}
+
+fn delayed<S: Sample>(delay: &mut Delay<S>, x: S) -> S {
+    let y = delay.output();
+    delay.input(x);
+    y
+}
@@ -1,35 +1,55 @@
-use super::Reverb;
+use super::{downmix, for_each_sub_block, Reverb, MAX_SIZE, SUB_BLOCK_SIZE};
+use crate::delay::{Delay, Interpolation, Transition};
 use crate::filter::{Allpass, FeedbackComb, Filter};
+use crate::sample::Sample;
 
 // https://ccrma.stanford.edu/~jos/pasp/Schroeder_Reverberators.html
-pub struct JCRev {
-    allpasses: [Allpass; 3],
-    combs: [FeedbackComb; 4],
+pub struct JCRev<S = f64> {
+    allpasses: [Allpass<S>; 3],
+    combs: [FeedbackComb<S>; 4],
+    sample_rate: u32,
 }
 
-impl JCRev {
+const ALLPASS_DELAYS: [u32; 3] = [347, 113, 37];
+const COMB_FEEDBACKS_AND_DELAYS: [(f64, u32); 4] = [
+    (-0.773, 1687),
+    (-0.802, 1601),
+    (-0.753, 2053),
+    (-0.733, 2251),
+];
+
+impl<S: Sample> JCRev<S> {
     pub fn new(sample_rate: u32) -> Self {
-        let scale_delay = |d| (f64::from(sample_rate) / 25000.0 * f64::from(d)) as usize;
-        macro_rules! allpasses_from_delays {
-            ($($delay:expr),*) => {[$(
-                Allpass::new(-0.7, -0.7, scale_delay($delay)),
-            )*]}
+        let scale = |d| scaled_length(sample_rate, d, 1.0);
+        let scale_delay =
+            |d| Delay::with_max_length(scale(d), MAX_SIZE * scale(d), Interpolation::Allpass);
+        Self {
+            allpasses: ALLPASS_DELAYS.map(|d| Allpass::new(-0.7, -0.7, scale_delay(d))),
+            combs: COMB_FEEDBACKS_AND_DELAYS.map(|(am, d)| FeedbackComb::new(am, scale_delay(d))),
+            sample_rate,
         }
-        macro_rules! combs_from_feedbacks_and_delays {
-            ($($am:expr, $delay:expr);*) => {[$(
-                FeedbackComb::new($am, scale_delay($delay)),
-            )*]}
+    }
+
+    /// Scales the lengths of all delays, where 1 is the original size and 2 is the maximum
+    pub fn set_size(&mut self, size: f64, transition: Transition) {
+        let sample_rate = self.sample_rate;
+        let scale = |d| scaled_length(sample_rate, d, size);
+        for (a, d) in self.allpasses.iter_mut().zip(&ALLPASS_DELAYS) {
+            a.delay_mut().set_length(scale(*d), transition);
         }
-        Self {
-            allpasses: allpasses_from_delays![347, 113, 37],
-            combs: combs_from_feedbacks_and_delays![-0.773, 1687; -0.802, 1601; -0.753, 2053; -0.733, 2251],
+        for (c, (_, d)) in self.combs.iter_mut().zip(&COMB_FEEDBACKS_AND_DELAYS) {
+            c.delay_mut().set_length(scale(*d), transition);
         }
     }
 }
 
-impl Reverb for JCRev {
-    fn process_sample(&mut self, x: (f64, f64)) -> (f64, f64) {
-        let input = (x.0 + x.1) / 2.0;
+fn scaled_length(sample_rate: u32, delay: u32, size: f64) -> f64 {
+    f64::from(sample_rate) / 25000.0 * f64::from(delay) * size
+}
+
+impl<S: Sample> Reverb<S> for JCRev<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let input = (x.0 + x.1) / S::from_f64(2.0);
 
         let allpass_output = self
             .allpasses
@@ -47,4 +67,25 @@ impl Reverb for JCRev {
             comb_output[1] + comb_output[3],
         )
     }
+
+    fn process_block(&mut self, input: (&[S], &[S]), output: (&mut [S], &mut [S])) {
+        for_each_sub_block(input, output, |input, output| {
+            let mut buf = [S::ZERO; SUB_BLOCK_SIZE];
+            let allpass_output = downmix(input, &mut buf);
+            for a in self.allpasses.iter_mut() {
+                a.process_block_in_place(allpass_output);
+            }
+
+            let [c0, c1, c2, c3] = &mut self.combs;
+            for (i, x) in allpass_output.iter().enumerate() {
+                output.0[i] = c0.process_sample(*x) + c2.process_sample(*x);
+                output.1[i] = c1.process_sample(*x) + c3.process_sample(*x);
+            }
+        });
+    }
+
+    fn reset(&mut self) {
+        self.allpasses.iter_mut().for_each(Filter::reset);
+        self.combs.iter_mut().for_each(Filter::reset);
+    }
 }
@@ -0,0 +1,134 @@
+use super::{Predelay, Reverb};
+use crate::delay::{Delay, DelayLine, Interpolation, Tap};
+use crate::filter::{pole_from_cutoff, Allpass, Filter, LowpassFeedbackComb};
+use crate::sample::Sample;
+
+// Moorer, J. A. (1979). About this reverberation business.
+// (time in seconds, gain) of the early reflections measured in Boston Symphony Hall,
+// without the direct sound at time 0
+const EARLY_REFLECTIONS: [(f64, f64); 18] = [
+    (0.0043, 0.841),
+    (0.0215, 0.504),
+    (0.0225, 0.491),
+    (0.0268, 0.379),
+    (0.0270, 0.380),
+    (0.0298, 0.346),
+    (0.0458, 0.289),
+    (0.0485, 0.272),
+    (0.0572, 0.192),
+    (0.0587, 0.193),
+    (0.0595, 0.217),
+    (0.0612, 0.181),
+    (0.0707, 0.180),
+    (0.0708, 0.181),
+    (0.0726, 0.176),
+    (0.0741, 0.142),
+    (0.0753, 0.167),
+    (0.0797, 0.134),
+];
+
+// (delay in ms, lowpass cutoff in Hz) of the combs.
+// the cutoffs are those of Moorer's coefficients from 0.46 to 0.55 at 25 kHz
+const COMBS: [(f64, f64); 6] = [
+    (50.0, 3090.0),
+    (56.0, 2920.0),
+    (61.0, 2758.0),
+    (68.0, 2602.0),
+    (72.0, 2526.0),
+    (78.0, 2379.0),
+];
+
+// early reflections from a tapped delay line feed lowpass combs for the late reverberation.
+// the even and odd combs go to the left and the right channels respectively
+pub struct Moorer<S = f64> {
+    predelay: Predelay<S>,
+    early: Delay<S>,
+    taps: Vec<Tap>,
+    combs: [LowpassFeedbackComb<S>; 6],
+    allpasses: [Allpass<S>; 2],
+    early_gain: S,
+    late_gain: S,
+}
+
+impl<S: Sample> Moorer<S> {
+    /// `t60` is the decay time at low frequencies, which the lowpass filters in the combs shorten for higher ones.
+    /// `balance` crossfades from only the early reflections at 0 to only the late reverberation at 1
+    pub fn new(sample_rate: u32, predelay_ms: f64, t60: f64, balance: f64) -> Self {
+        let fs = f64::from(sample_rate);
+        let taps: Vec<_> = EARLY_REFLECTIONS
+            .iter()
+            .map(|(t, gain)| Tap {
+                position: fs * t,
+                gain: *gain,
+            })
+            .collect();
+        let max_length = taps.iter().map(|tap| tap.position).fold(0.0, f64::max);
+        let comb = |(ms, cutoff): (f64, f64)| {
+            let feedback = f64::powf(10.0, -3.0 * ms / 1000.0 / t60);
+            LowpassFeedbackComb::new(
+                feedback,
+                pole_from_cutoff(sample_rate, cutoff),
+                Delay::from_millis(sample_rate, ms, Interpolation::Allpass),
+            )
+        };
+        let allpass = || {
+            Allpass::new(
+                -0.7,
+                -0.7,
+                Delay::from_millis(sample_rate, 6.0, Interpolation::Allpass),
+            )
+        };
+        let balance = balance.clamp(0.0, 1.0);
+        Self {
+            predelay: Predelay::new(sample_rate, predelay_ms),
+            early: Delay::with_max_length(max_length, max_length, Interpolation::Linear),
+            taps,
+            combs: COMBS.map(comb),
+            allpasses: [allpass(), allpass()],
+            // roughly matches the loudness of both parts
+            early_gain: S::from_f64(0.75 * (1.0 - balance)),
+            late_gain: S::from_f64(0.35 * balance),
+        }
+    }
+
+    fn process_mono(&mut self, x: S) -> (S, S) {
+        let delayed = self.predelay.process_sample(x);
+        let early = self.early.taps_output(&self.taps);
+        self.early.input(delayed);
+
+        let mut late = (S::ZERO, S::ZERO);
+        for (i, c) in self.combs.iter_mut().enumerate() {
+            // the delayed part only, so that the early reflections are not repeated in the late part
+            let y = c.delay().output();
+            c.process_sample(early);
+            if i % 2 == 0 {
+                late.0 += y;
+            } else {
+                late.1 += y;
+            }
+        }
+        let late = (
+            self.allpasses[0].process_sample(late.0),
+            self.allpasses[1].process_sample(late.1),
+        );
+
+        let early = self.early_gain * early;
+        (
+            early + self.late_gain * late.0,
+            early + self.late_gain * late.1,
+        )
+    }
+}
+
+impl<S: Sample> Reverb<S> for Moorer<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        self.process_mono((x.0 + x.1) / S::from_f64(2.0))
+    }
+
+    fn reset(&mut self) {
+        self.predelay.reset();
+        self.early.reset();
+        self.combs.iter_mut().for_each(Filter::reset);
+        self.allpasses.iter_mut().for_each(Filter::reset);
+    }
+}
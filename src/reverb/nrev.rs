@@ -1,17 +1,24 @@
-use super::Reverb;
-use crate::filter::{Allpass, FeedbackComb, FeedforwardComb, Filter};
+use super::{downmix, for_each_sub_block, Reverb, SUB_BLOCK_SIZE};
+use crate::delay::{Delay, Interpolation};
+use crate::filter::{Allpass, FeedbackComb, Filter, OnePoleLowpass};
+use crate::sample::Sample;
 
 // https://ccrma.stanford.edu/software/stk/
 // https://github.com/thestk/stk/blob/master/include/NRev.h
-pub struct NRev {
-    fb_combs: [FeedbackComb; 6],
-    ff_comb: FeedforwardComb,
-    allpasses: [Allpass; 6],
+pub struct NRev<S = f64> {
+    fb_combs: [FeedbackComb<S>; 6],
+    lowpass: OnePoleLowpass<S>,
+    allpasses: [Allpass<S>; 6],
 }
 
-impl NRev {
+impl<S: Sample> NRev<S> {
     pub fn new(sample_rate: u32, t60: f64) -> Self {
-        let scale_delay = |d| (f64::from(sample_rate) / 25641.0 * f64::from(d)) as usize;
+        let scale_delay = |d| {
+            Delay::fractional(
+                f64::from(sample_rate) / 25641.0 * f64::from(d),
+                Interpolation::Allpass,
+            )
+        };
         macro_rules! combs_from_delays {
             ($($delay:expr),*) => {[$(
                 FeedbackComb::new(-(10.0 as f64).powf(-3.0 * f64::from($delay) / (44100.0 * t60)), scale_delay($delay)),
@@ -24,15 +31,15 @@ impl NRev {
         }
         Self {
             fb_combs: combs_from_delays![1433, 1601, 1867, 2053, 2251, 2399],
-            ff_comb: FeedforwardComb::new(0.3, 0.7, 1),
+            lowpass: OnePoleLowpass::from_pole(0.7),
             allpasses: allpasses_from_delays![347, 113, 37, 59, 53, 43],
         }
     }
 }
 
-impl Reverb for NRev {
-    fn process_sample(&mut self, x: (f64, f64)) -> (f64, f64) {
-        let input = (x.0 + x.1) / 2.0;
+impl<S: Sample> Reverb<S> for NRev<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let input = (x.0 + x.1) / S::from_f64(2.0);
 
         let comb_output = self
             .fb_combs
@@ -45,13 +52,52 @@ impl Reverb for NRev {
             .fold(comb_output, |output, a| a.process_sample(output));
 
         let lowpass_output =
-            self.allpasses[3].process_sample(self.ff_comb.process_sample(allpass_output));
+            self.allpasses[3].process_sample(self.lowpass.process_sample(allpass_output));
 
         let output: Vec<_> = self.allpasses[4..6]
             .iter_mut()
-            .map(|a| 0.3 * a.process_sample(lowpass_output) + 0.7 * input)
+            .map(|a| S::from_f64(0.3) * a.process_sample(lowpass_output) + S::from_f64(0.7) * input)
             .collect();
 
         (output[0], output[1])
     }
+
+    fn process_block(&mut self, input: (&[S], &[S]), output: (&mut [S], &mut [S])) {
+        for_each_sub_block(input, output, |input, output| {
+            let mut buf = [S::ZERO; SUB_BLOCK_SIZE];
+            let input = downmix(input, &mut buf);
+
+            let mut comb_output = [S::ZERO; SUB_BLOCK_SIZE];
+            let comb_output = &mut comb_output[..input.len()];
+            for c in self.fb_combs.iter_mut() {
+                for (y, x) in comb_output.iter_mut().zip(input.iter()) {
+                    *y += c.process_sample(*x);
+                }
+            }
+
+            for a in self.allpasses[0..3].iter_mut() {
+                a.process_block_in_place(comb_output);
+            }
+            self.lowpass.process_block_in_place(comb_output);
+            self.allpasses[3].process_block_in_place(comb_output);
+
+            let (a4, a5) = self.allpasses[4..6].split_at_mut(1);
+            for (i, (x, l)) in input.iter().zip(comb_output.iter()).enumerate() {
+                output.0[i] = S::from_f64(0.3) * a4[0].process_sample(*l) + S::from_f64(0.7) * *x;
+                output.1[i] = S::from_f64(0.3) * a5[0].process_sample(*l) + S::from_f64(0.7) * *x;
+            }
+        });
+    }
+
+    fn dry_output(&self, x: (S, S)) -> (S, S) {
+        let input = (x.0 + x.1) / S::from_f64(2.0);
+        let dry = S::from_f64(0.7) * input;
+        (dry, dry)
+    }
+
+    fn reset(&mut self) {
+        self.fb_combs.iter_mut().for_each(Filter::reset);
+        self.lowpass.reset();
+        self.allpasses.iter_mut().for_each(Filter::reset);
+    }
 }
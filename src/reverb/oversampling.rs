@@ -0,0 +1,277 @@
+use super::{for_each_sub_block, Reverb, SUB_BLOCK_SIZE};
+use crate::sample::Sample;
+use std::f64::consts::PI;
+
+const MAX_FACTOR: usize = 8;
+
+// the first stage needs a sharp transition band to keep the audible band intact,
+// later stages only need to reject images far above it
+const FIRST_STAGE_HALF_LENGTH: usize = 32;
+const LATER_STAGE_HALF_LENGTH: usize = 8;
+
+/// Runs any reverb at 2, 4 or 8 times the sample rate,
+/// resampling with cascaded polyphase halfband FIR filters
+pub struct Oversampled<R, S = f64> {
+    reverb: R,
+    factor: usize,
+    upsamplers: [Vec<Upsampler<S>>; 2],
+    downsamplers: [Vec<Downsampler<S>>; 2],
+}
+
+impl<R: Reverb<S>, S: Sample> Oversampled<R, S> {
+    /// `build` is called with the oversampled rate to construct the inner reverb
+    pub fn new<F: FnOnce(u32) -> R>(sample_rate: u32, factor: usize, build: F) -> Self {
+        assert!(
+            factor == 2 || factor == 4 || factor == 8,
+            "oversampling factor must be 2, 4 or 8"
+        );
+        let stages = factor.trailing_zeros() as usize;
+        let coefficients: Vec<_> = (0..stages).map(|i| halfband(half_length(i))).collect();
+        let upsamplers = || coefficients.iter().map(|c| Upsampler::new(c)).collect();
+        let downsamplers = || coefficients.iter().map(|c| Downsampler::new(c)).collect();
+        Self {
+            reverb: build(sample_rate * factor as u32),
+            factor,
+            upsamplers: [upsamplers(), upsamplers()],
+            downsamplers: [downsamplers(), downsamplers()],
+        }
+    }
+
+    pub fn reverb(&self) -> &R {
+        &self.reverb
+    }
+
+    pub fn reverb_mut(&mut self) -> &mut R {
+        &mut self.reverb
+    }
+
+    /// Delay added by resampling, in samples at the original rate
+    pub fn latency(&self) -> f64 {
+        // each stage delays by its center tap on both the way up and the way down
+        (0..self.upsamplers[0].len())
+            .map(|i| {
+                let center = 2 * half_length(i) - 1;
+                2.0 * center as f64 / f64::from(2u32 << i)
+            })
+            .sum()
+    }
+
+    // writes `factor` samples to the beginning of `output`
+    fn upsample(&mut self, channel: usize, x: S, output: &mut [S]) {
+        output[0] = x;
+        let mut len = 1;
+        for stage in self.upsamplers[channel].iter_mut() {
+            // samples must be fed in order, so they cannot be expanded in place
+            let mut input = [S::ZERO; MAX_FACTOR];
+            input[..len].copy_from_slice(&output[..len]);
+            for (i, x) in input[..len].iter().enumerate() {
+                let (y0, y1) = stage.process_sample(*x);
+                output[2 * i] = y0;
+                output[2 * i + 1] = y1;
+            }
+            len *= 2;
+        }
+    }
+
+    // consumes `factor` samples of `input`
+    fn downsample(&mut self, channel: usize, input: &mut [S]) -> S {
+        let mut len = self.factor;
+        for stage in self.downsamplers[channel].iter_mut().rev() {
+            len /= 2;
+            for i in 0..len {
+                input[i] = stage.process_sample((input[2 * i], input[2 * i + 1]));
+            }
+        }
+        input[0]
+    }
+}
+
+impl<R: Reverb<S>, S: Sample> Reverb<S> for Oversampled<R, S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let factor = self.factor;
+        let mut buf = [[S::ZERO; MAX_FACTOR]; 4];
+        let [l_in, r_in, l_out, r_out] = &mut buf;
+        self.upsample(0, x.0, l_in);
+        self.upsample(1, x.1, r_in);
+        for i in 0..factor {
+            let y = self.reverb.process_sample((l_in[i], r_in[i]));
+            l_out[i] = y.0;
+            r_out[i] = y.1;
+        }
+        (self.downsample(0, l_out), self.downsample(1, r_out))
+    }
+
+    fn process_block(&mut self, input: (&[S], &[S]), output: (&mut [S], &mut [S])) {
+        let factor = self.factor;
+        for_each_sub_block(input, output, |x, output| {
+            let len = factor * x.0.len();
+            let mut buf = [[S::ZERO; MAX_FACTOR * SUB_BLOCK_SIZE]; 4];
+            let [l_in, r_in, l_out, r_out] = &mut buf;
+            for (i, x) in x.0.iter().zip(x.1).enumerate() {
+                self.upsample(0, *x.0, &mut l_in[factor * i..]);
+                self.upsample(1, *x.1, &mut r_in[factor * i..]);
+            }
+
+            self.reverb.process_block(
+                (&l_in[..len], &r_in[..len]),
+                (&mut l_out[..len], &mut r_out[..len]),
+            );
+
+            for (i, (l, r)) in l_out[..len]
+                .chunks_exact_mut(factor)
+                .zip(r_out[..len].chunks_exact_mut(factor))
+                .enumerate()
+            {
+                output.0[i] = self.downsample(0, l);
+                output.1[i] = self.downsample(1, r);
+            }
+        });
+    }
+
+    fn reset(&mut self) {
+        self.reverb.reset();
+        for upsamplers in self.upsamplers.iter_mut() {
+            upsamplers.iter_mut().for_each(Upsampler::reset);
+        }
+        for downsamplers in self.downsamplers.iter_mut() {
+            downsamplers.iter_mut().for_each(Downsampler::reset);
+        }
+    }
+}
+
+fn half_length(stage: usize) -> usize {
+    if stage == 0 {
+        FIRST_STAGE_HALF_LENGTH
+    } else {
+        LATER_STAGE_HALF_LENGTH
+    }
+}
+
+// Kaiser-windowed halfband lowpass of length 4 * half_length - 1, whose every other
+// tap is zero except for the center tap of 0.5. returns only the nonzero off-center taps,
+// which form one polyphase branch
+fn halfband(half_length: usize) -> Vec<f64> {
+    const BETA: f64 = 8.0;
+    let center = 2 * half_length - 1;
+    let taps: Vec<_> = (0..2 * half_length)
+        .map(|i| {
+            let k = 2 * i as isize - center as isize;
+            let sinc = (PI * k as f64 / 2.0).sin() / (PI * k as f64);
+            let r = k as f64 / center as f64;
+            let window = bessel_i0(BETA * (1.0 - r * r).sqrt()) / bessel_i0(BETA);
+            sinc * window
+        })
+        .collect();
+    // normalize for unity gain at DC
+    let sum: f64 = taps.iter().sum();
+    taps.iter().map(|h| 0.5 * h / sum).collect()
+}
+
+// zeroth-order modified Bessel function of the first kind
+pub(super) fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    for k in 1..50 {
+        term *= (x / (2.0 * k as f64)).powi(2);
+        sum += term;
+        if term < 1e-12 * sum {
+            break;
+        }
+    }
+    sum
+}
+
+// history of the last `n` inputs, duplicated so that it can be read as a contiguous slice
+struct History<S> {
+    buffer: Vec<S>,
+    pos: usize,
+}
+
+impl<S: Sample> History<S> {
+    fn new(n: usize) -> Self {
+        Self {
+            buffer: vec![S::ZERO; 2 * n],
+            pos: 0,
+        }
+    }
+
+    fn push(&mut self, x: S) {
+        let n = self.buffer.len() / 2;
+        self.pos = if self.pos == 0 { n - 1 } else { self.pos - 1 };
+        self.buffer[self.pos] = x;
+        self.buffer[self.pos + n] = x;
+    }
+
+    // most recent first
+    fn as_slice(&self) -> &[S] {
+        &self.buffer[self.pos..self.pos + self.buffer.len() / 2]
+    }
+
+    fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|x| *x = S::ZERO);
+    }
+}
+
+// doubles the rate, outputting the even and the odd sample for each input
+struct Upsampler<S> {
+    taps: Vec<S>,
+    history: History<S>,
+}
+
+impl<S: Sample> Upsampler<S> {
+    fn new(taps: &[f64]) -> Self {
+        Self {
+            // compensates for the zeros inserted between samples
+            taps: taps.iter().map(|h| S::from_f64(2.0 * h)).collect(),
+            history: History::new(taps.len()),
+        }
+    }
+
+    fn process_sample(&mut self, x: S) -> (S, S) {
+        self.history.push(x);
+        let history = self.history.as_slice();
+        let even = self.taps.iter().zip(history).map(|(h, x)| *h * *x).sum();
+        // the center tap lies between the middle two taps of the branch
+        let odd = history[self.taps.len() / 2 - 1];
+        (even, odd)
+    }
+
+    fn reset(&mut self) {
+        self.history.reset();
+    }
+}
+
+// halves the rate, taking pairs of even and odd samples
+struct Downsampler<S> {
+    taps: Vec<S>,
+    even: History<S>,
+    odd: History<S>,
+}
+
+impl<S: Sample> Downsampler<S> {
+    fn new(taps: &[f64]) -> Self {
+        Self {
+            taps: taps.iter().map(|h| S::from_f64(*h)).collect(),
+            even: History::new(taps.len()),
+            odd: History::new(taps.len() / 2 + 1),
+        }
+    }
+
+    fn process_sample(&mut self, x: (S, S)) -> S {
+        self.even.push(x.0);
+        let even: S = self
+            .taps
+            .iter()
+            .zip(self.even.as_slice())
+            .map(|(h, x)| *h * *x)
+            .sum();
+        let odd = self.odd.as_slice()[self.taps.len() / 2 - 1];
+        self.odd.push(x.1);
+        even + S::from_f64(0.5) * odd
+    }
+
+    fn reset(&mut self) {
+        self.even.reset();
+        self.odd.reset();
+    }
+}
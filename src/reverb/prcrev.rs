@@ -1,16 +1,23 @@
-use super::Reverb;
+use super::{downmix, for_each_sub_block, Reverb, SUB_BLOCK_SIZE};
+use crate::delay::{Delay, Interpolation};
 use crate::filter::{Allpass, FeedbackComb, Filter};
+use crate::sample::Sample;
 
 // https://ccrma.stanford.edu/software/stk/
 // https://github.com/thestk/stk/blob/master/include/PRCRev.h
-pub struct PRCRev {
-    allpasses: [Allpass; 2],
-    combs: [FeedbackComb; 2],
+pub struct PRCRev<S = f64> {
+    allpasses: [Allpass<S>; 2],
+    combs: [FeedbackComb<S>; 2],
 }
 
-impl PRCRev {
+impl<S: Sample> PRCRev<S> {
     pub fn new(sample_rate: u32, t60: f64) -> Self {
-        let scale_delay = |d| (f64::from(sample_rate) / 44100.0 * f64::from(d)) as usize;
+        let scale_delay = |d| {
+            Delay::fractional(
+                f64::from(sample_rate) / 44100.0 * f64::from(d),
+                Interpolation::Allpass,
+            )
+        };
         macro_rules! allpasses_from_delays {
             ($($delay:expr),*) => {[$(
                 Allpass::new(-0.7, -0.7, scale_delay($delay)),
@@ -28,9 +35,9 @@ impl PRCRev {
     }
 }
 
-impl Reverb for PRCRev {
-    fn process_sample(&mut self, x: (f64, f64)) -> (f64, f64) {
-        let input = (x.0 + x.1) / 2.0;
+impl<S: Sample> Reverb<S> for PRCRev<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let input = (x.0 + x.1) / S::from_f64(2.0);
 
         let allpass_output = self
             .allpasses
@@ -40,9 +47,40 @@ impl Reverb for PRCRev {
         let comb_output: Vec<_> = self
             .combs
             .iter_mut()
-            .map(|c| 0.5 * (input + c.process_sample(allpass_output)))
+            .map(|c| S::from_f64(0.5) * (input + c.process_sample(allpass_output)))
             .collect();
 
         (comb_output[0], comb_output[1])
     }
+
+    fn process_block(&mut self, input: (&[S], &[S]), output: (&mut [S], &mut [S])) {
+        for_each_sub_block(input, output, |input, output| {
+            let mut buf = [S::ZERO; SUB_BLOCK_SIZE];
+            let input = downmix(input, &mut buf);
+
+            let mut allpass_output = [S::ZERO; SUB_BLOCK_SIZE];
+            let allpass_output = &mut allpass_output[..input.len()];
+            allpass_output.copy_from_slice(input);
+            for a in self.allpasses.iter_mut() {
+                a.process_block_in_place(allpass_output);
+            }
+
+            let [c0, c1] = &mut self.combs;
+            for (i, (x, a)) in input.iter().zip(allpass_output.iter()).enumerate() {
+                output.0[i] = S::from_f64(0.5) * (*x + c0.process_sample(*a));
+                output.1[i] = S::from_f64(0.5) * (*x + c1.process_sample(*a));
+            }
+        });
+    }
+
+    fn dry_output(&self, x: (S, S)) -> (S, S) {
+        let input = (x.0 + x.1) / S::from_f64(2.0);
+        let dry = S::from_f64(0.5) * input;
+        (dry, dry)
+    }
+
+    fn reset(&mut self) {
+        self.allpasses.iter_mut().for_each(Filter::reset);
+        self.combs.iter_mut().for_each(Filter::reset);
+    }
 }
@@ -1,15 +1,22 @@
-use super::Reverb;
+use super::{downmix, for_each_sub_block, Reverb, SUB_BLOCK_SIZE};
+use crate::delay::{Delay, Interpolation};
 use crate::filter::{Allpass, FeedbackComb, Filter};
+use crate::sample::Sample;
 
 // https://ccrma.stanford.edu/~jos/pasp/Example_Schroeder_Reverberators.html
-pub struct SATREV {
-    combs: [FeedbackComb; 4],
-    allpasses: [Allpass; 3],
+pub struct SATREV<S = f64> {
+    combs: [FeedbackComb<S>; 4],
+    allpasses: [Allpass<S>; 3],
 }
 
-impl SATREV {
+impl<S: Sample> SATREV<S> {
     pub fn new(sample_rate: u32) -> Self {
-        let scale_delay = |d| (f64::from(sample_rate) / 25000.0 * f64::from(d)) as usize;
+        let scale_delay = |d| {
+            Delay::fractional(
+                f64::from(sample_rate) / 25000.0 * f64::from(d),
+                Interpolation::Allpass,
+            )
+        };
         macro_rules! combs_from_feedbacks_and_delays {
             ($($am:expr, $delay:expr);*) => {[$(
                 FeedbackComb::new($am, scale_delay($delay)),
@@ -27,9 +34,9 @@ impl SATREV {
     }
 }
 
-impl Reverb for SATREV {
-    fn process_sample(&mut self, x: (f64, f64)) -> (f64, f64) {
-        let input = (x.0 + x.1) / 2.0;
+impl<S: Sample> Reverb<S> for SATREV<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let input = (x.0 + x.1) / S::from_f64(2.0);
 
         let comb_output = self.combs.iter_mut().map(|c| c.process_sample(input)).sum();
 
@@ -40,4 +47,31 @@ impl Reverb for SATREV {
 
         (allpass_output, -allpass_output)
     }
+
+    fn process_block(&mut self, input: (&[S], &[S]), output: (&mut [S], &mut [S])) {
+        for_each_sub_block(input, output, |input, output| {
+            let mut buf = [S::ZERO; SUB_BLOCK_SIZE];
+            let input = downmix(input, &mut buf);
+
+            output.0.iter_mut().for_each(|y| *y = S::ZERO);
+            for c in self.combs.iter_mut() {
+                for (y, x) in output.0.iter_mut().zip(input.iter()) {
+                    *y += c.process_sample(*x);
+                }
+            }
+
+            for a in self.allpasses.iter_mut() {
+                a.process_block_in_place(output.0);
+            }
+
+            for (r, l) in output.1.iter_mut().zip(output.0.iter()) {
+                *r = -*l;
+            }
+        });
+    }
+
+    fn reset(&mut self) {
+        self.combs.iter_mut().for_each(Filter::reset);
+        self.allpasses.iter_mut().for_each(Filter::reset);
+    }
 }
@@ -1,18 +1,21 @@
-use super::Reverb;
-use crate::delay::Delay;
-use crate::filter::{Allpass, FeedforwardComb, Filter};
+use super::{downmix, for_each_sub_block, Reverb, SUB_BLOCK_SIZE};
+use crate::delay::{Delay, DelayLine, Interpolation};
+use crate::filter::{Allpass, Filter, OnePoleLowpass};
+use crate::sample::Sample;
 
 // https://ccrma.stanford.edu/software/stk/
 // https://github.com/thestk/stk/blob/master/include/JCRev.h
-pub struct STKJCRev {
-    allpasses: [Allpass; 3],
-    combs: [(Delay, FeedforwardComb); 4],
-    out_delays: [Delay; 2],
+pub struct STKJCRev<S = f64> {
+    allpasses: [Allpass<S>; 3],
+    combs: [(Delay<S>, OnePoleLowpass<S>, S); 4],
+    out_delay: Delay<S>,
+    out_taps: [f64; 2],
 }
 
-impl STKJCRev {
+impl<S: Sample> STKJCRev<S> {
     pub fn new(sample_rate: u32, t60: f64) -> Self {
-        let scale_delay = |d| (f64::from(sample_rate) / 44100.0 * f64::from(d)) as usize;
+        let scale = |d| f64::from(sample_rate) / 44100.0 * f64::from(d);
+        let scale_delay = |d| Delay::fractional(scale(d), Interpolation::Allpass);
         macro_rules! allpasses_from_delays {
             ($($delay:expr),*) => {[$(
                 Allpass::new(-0.7, -0.7, scale_delay($delay)),
@@ -21,28 +24,24 @@ impl STKJCRev {
         macro_rules! combs_from_delays {
             ($($delay:expr),*) => {[$(
                 (
-                    Delay::new(scale_delay($delay)),
-                    FeedforwardComb::new(
-                        0.8 * (10.0 as f64).powf(-3.0 * f64::from($delay) / (44100.0 * t60)), 0.2, scale_delay($delay)),
+                    scale_delay($delay),
+                    OnePoleLowpass::from_pole(0.2),
+                    S::from_f64((10.0 as f64).powf(-3.0 * f64::from($delay) / (44100.0 * t60))),
                 ),
             )*]}
         }
-        macro_rules! delays {
-            ($($delay:expr),*) => {[$(
-                Delay::new(scale_delay($delay)),
-            )*]}
-        }
         Self {
             allpasses: allpasses_from_delays![225, 341, 441],
             combs: combs_from_delays![1116, 1356, 1422, 1617],
-            out_delays: delays![211, 179],
+            out_delay: Delay::fractional(scale(211), Interpolation::Lagrange),
+            out_taps: [scale(211), scale(179)],
         }
     }
 }
 
-impl Reverb for STKJCRev {
-    fn process_sample(&mut self, x: (f64, f64)) -> (f64, f64) {
-        let input = (x.0 + x.1) / 2.0;
+impl<S: Sample> Reverb<S> for STKJCRev<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let input = (x.0 + x.1) / S::from_f64(2.0);
 
         let allpass_output = self
             .allpasses
@@ -52,21 +51,71 @@ impl Reverb for STKJCRev {
         let comb_output = self
             .combs
             .iter_mut()
-            .map(|(delay, filter)| {
-                let output = allpass_output + filter.process_sample(delay.output());
+            .map(|(delay, lowpass, coefficient)| {
+                let output = allpass_output + *coefficient * lowpass.process_sample(delay.output());
                 delay.input(output);
                 output
             })
             .sum();
 
         let output = (
-            0.7 * (0.3 * self.out_delays[0].output() + 0.7 * input),
-            0.7 * (0.3 * self.out_delays[1].output() + 0.7 * input),
+            S::from_f64(0.7)
+                * (S::from_f64(0.3) * self.out_delay.tap_output(self.out_taps[0])
+                    + S::from_f64(0.7) * input),
+            S::from_f64(0.7)
+                * (S::from_f64(0.3) * self.out_delay.tap_output(self.out_taps[1])
+                    + S::from_f64(0.7) * input),
         );
 
-        self.out_delays[0].input(comb_output);
-        self.out_delays[1].input(comb_output);
+        self.out_delay.input(comb_output);
 
         output
     }
+
+    fn process_block(&mut self, input: (&[S], &[S]), output: (&mut [S], &mut [S])) {
+        for_each_sub_block(input, output, |input, output| {
+            let mut buf = [S::ZERO; SUB_BLOCK_SIZE];
+            let input = downmix(input, &mut buf);
+
+            let mut allpass_output = [S::ZERO; SUB_BLOCK_SIZE];
+            let allpass_output = &mut allpass_output[..input.len()];
+            allpass_output.copy_from_slice(input);
+            for a in self.allpasses.iter_mut() {
+                a.process_block_in_place(allpass_output);
+            }
+
+            for (i, (x, a)) in input.iter().zip(allpass_output.iter()).enumerate() {
+                let mut comb_output = S::ZERO;
+                for (delay, lowpass, coefficient) in self.combs.iter_mut() {
+                    let output = *a + *coefficient * lowpass.process_sample(delay.output());
+                    delay.input(output);
+                    comb_output += output;
+                }
+
+                output.0[i] = S::from_f64(0.7)
+                    * (S::from_f64(0.3) * self.out_delay.tap_output(self.out_taps[0])
+                        + S::from_f64(0.7) * *x);
+                output.1[i] = S::from_f64(0.7)
+                    * (S::from_f64(0.3) * self.out_delay.tap_output(self.out_taps[1])
+                        + S::from_f64(0.7) * *x);
+
+                self.out_delay.input(comb_output);
+            }
+        });
+    }
+
+    fn dry_output(&self, x: (S, S)) -> (S, S) {
+        let input = (x.0 + x.1) / S::from_f64(2.0);
+        let dry = S::from_f64(0.7) * (S::from_f64(0.7) * input);
+        (dry, dry)
+    }
+
+    fn reset(&mut self) {
+        self.allpasses.iter_mut().for_each(Filter::reset);
+        for (delay, lowpass, _) in self.combs.iter_mut() {
+            delay.reset();
+            lowpass.reset();
+        }
+        self.out_delay.reset();
+    }
 }
@@ -0,0 +1,152 @@
+use super::{for_each_sub_block, Reverb, SUB_BLOCK_SIZE};
+use crate::filter::{Biquad, Filter, Tilt};
+use crate::sample::Sample;
+
+const BUTTERWORTH_Q: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+/// Shapes the input and the output of any reverb.
+/// The output EQ applies to everything the reverb outputs, including its dry signal
+pub struct ToneControl<R, S = f64> {
+    reverb: R,
+    sample_rate: u32,
+    low_cut: Option<[Biquad<S>; 2]>,
+    high_cut: Option<[Biquad<S>; 2]>,
+    tilt: [Tilt<S>; 2],
+    low_shelf: [Biquad<S>; 2],
+    high_shelf: [Biquad<S>; 2],
+}
+
+impl<R: Reverb<S>, S: Sample> ToneControl<R, S> {
+    /// All stages are initially neutral
+    pub fn new(reverb: R, sample_rate: u32) -> Self {
+        Self {
+            reverb,
+            sample_rate,
+            low_cut: None,
+            high_cut: None,
+            tilt: [
+                Tilt::new(sample_rate, 1000.0, 0.0),
+                Tilt::new(sample_rate, 1000.0, 0.0),
+            ],
+            low_shelf: [
+                Biquad::low_shelf(sample_rate, 200.0, BUTTERWORTH_Q, 0.0),
+                Biquad::low_shelf(sample_rate, 200.0, BUTTERWORTH_Q, 0.0),
+            ],
+            high_shelf: [
+                Biquad::high_shelf(sample_rate, 5000.0, BUTTERWORTH_Q, 0.0),
+                Biquad::high_shelf(sample_rate, 5000.0, BUTTERWORTH_Q, 0.0),
+            ],
+        }
+    }
+
+    pub fn reverb(&self) -> &R {
+        &self.reverb
+    }
+
+    pub fn reverb_mut(&mut self) -> &mut R {
+        &mut self.reverb
+    }
+
+    // setters below replace the filters of the stage, clearing their state
+
+    /// Highpasses the input at `cutoff` Hz with 12 dB/oct, or disables it with `None`
+    pub fn set_low_cut(&mut self, cutoff: Option<f64>) {
+        let sample_rate = self.sample_rate;
+        self.low_cut = cutoff.map(|f| {
+            [
+                Biquad::highpass(sample_rate, f, BUTTERWORTH_Q),
+                Biquad::highpass(sample_rate, f, BUTTERWORTH_Q),
+            ]
+        });
+    }
+
+    /// Lowpasses the input at `cutoff` Hz with 12 dB/oct, or disables it with `None`
+    pub fn set_high_cut(&mut self, cutoff: Option<f64>) {
+        let sample_rate = self.sample_rate;
+        self.high_cut = cutoff.map(|f| {
+            [
+                Biquad::lowpass(sample_rate, f, BUTTERWORTH_Q),
+                Biquad::lowpass(sample_rate, f, BUTTERWORTH_Q),
+            ]
+        });
+    }
+
+    /// Tilts the output around `pivot` Hz, see `Tilt::new`
+    pub fn set_tilt(&mut self, pivot: f64, gain_db: f64) {
+        self.tilt = [
+            Tilt::new(self.sample_rate, pivot, gain_db),
+            Tilt::new(self.sample_rate, pivot, gain_db),
+        ];
+    }
+
+    pub fn set_low_shelf(&mut self, freq: f64, gain_db: f64) {
+        self.low_shelf = [
+            Biquad::low_shelf(self.sample_rate, freq, BUTTERWORTH_Q, gain_db),
+            Biquad::low_shelf(self.sample_rate, freq, BUTTERWORTH_Q, gain_db),
+        ];
+    }
+
+    pub fn set_high_shelf(&mut self, freq: f64, gain_db: f64) {
+        self.high_shelf = [
+            Biquad::high_shelf(self.sample_rate, freq, BUTTERWORTH_Q, gain_db),
+            Biquad::high_shelf(self.sample_rate, freq, BUTTERWORTH_Q, gain_db),
+        ];
+    }
+
+    fn shape_input(&mut self, channel: usize, x: S) -> S {
+        let x = match &mut self.low_cut {
+            Some(f) => f[channel].process_sample(x),
+            None => x,
+        };
+        match &mut self.high_cut {
+            Some(f) => f[channel].process_sample(x),
+            None => x,
+        }
+    }
+
+    fn shape_output(&mut self, channel: usize, y: S) -> S {
+        let y = self.tilt[channel].process_sample(y);
+        let y = self.low_shelf[channel].process_sample(y);
+        self.high_shelf[channel].process_sample(y)
+    }
+}
+
+impl<R: Reverb<S>, S: Sample> Reverb<S> for ToneControl<R, S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let x = (self.shape_input(0, x.0), self.shape_input(1, x.1));
+        let y = self.reverb.process_sample(x);
+        (self.shape_output(0, y.0), self.shape_output(1, y.1))
+    }
+
+    fn process_block(&mut self, input: (&[S], &[S]), output: (&mut [S], &mut [S])) {
+        for_each_sub_block(input, output, |x, output| {
+            let mut buf = [[S::ZERO; SUB_BLOCK_SIZE]; 2];
+            let (l_buf, r_buf) = buf.split_at_mut(1);
+            let (l, r) = (&mut l_buf[0][..x.0.len()], &mut r_buf[0][..x.0.len()]);
+            for (i, x) in x.0.iter().zip(x.1).enumerate() {
+                l[i] = self.shape_input(0, *x.0);
+                r[i] = self.shape_input(1, *x.1);
+            }
+
+            self.reverb
+                .process_block((l, r), (&mut *output.0, &mut *output.1));
+
+            for y in output.0.iter_mut() {
+                *y = self.shape_output(0, *y);
+            }
+            for y in output.1.iter_mut() {
+                *y = self.shape_output(1, *y);
+            }
+        });
+    }
+
+    fn reset(&mut self) {
+        self.reverb.reset();
+        for f in self.low_cut.iter_mut().chain(self.high_cut.iter_mut()) {
+            f.iter_mut().for_each(Filter::reset);
+        }
+        self.tilt.iter_mut().for_each(Filter::reset);
+        self.low_shelf.iter_mut().for_each(Filter::reset);
+        self.high_shelf.iter_mut().for_each(Filter::reset);
+    }
+}
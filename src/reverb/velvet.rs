@@ -0,0 +1,126 @@
+use super::Reverb;
+use crate::filter::{Filter, OnePoleLowpass};
+use crate::rng::Rng;
+use crate::sample::Sample;
+
+// the decay and the lowpass filter are updated once per segment of this length
+const SEGMENT_MS: f64 = 20.0;
+
+// the cutoff of the lowpass filters falls exponentially between these
+// from the beginning of the tail to `t60` seconds
+const CUTOFF_START: f64 = 12000.0;
+const CUTOFF_END: f64 = 1000.0;
+
+// Välimäki, V., Holm-Rasmussen, B., Alary, B., & Lehtonen, H.-M. (2017).
+// Late reverberation synthesis using filtered velvet noise.
+// the tail is a sparse convolution with velvet noise, which only needs additions and subtractions,
+// and each segment of it is scaled and lowpass filtered to decay faster at high frequencies
+pub struct VelvetNoise<S = f64> {
+    // past input whose length is a power of two, the latest sample at `write_ptr`
+    history: Vec<S>,
+    write_ptr: usize,
+    segments: [Vec<Segment<S>>; 2],
+}
+
+struct Segment<S> {
+    // positions of the +1 and -1 pulses
+    positive: Vec<usize>,
+    negative: Vec<usize>,
+    lowpass: OnePoleLowpass<S>,
+    gain: S,
+}
+
+impl<S: Sample> VelvetNoise<S> {
+    /// `density` is the number of pulses per second, from around 1500 of which the tail sounds smooth.
+    /// The cost per sample is proportional to `density * t60`.
+    /// Each channel gets its own sequence of pulses, which is determined by `seed`
+    pub fn new(sample_rate: u32, density: f64, t60: f64, seed: u64) -> Self {
+        let fs = f64::from(sample_rate);
+        let grid = (fs / density).max(1.0);
+        let segment_length = (fs * SEGMENT_MS / 1000.0).round() as usize;
+        let segment_count = (t60 * 1000.0 / SEGMENT_MS).ceil().max(1.0) as usize;
+        let length = segment_length * segment_count;
+
+        let mut rng = Rng::new(seed);
+        let mut channel = || {
+            let mut segments: Vec<_> = (0..segment_count)
+                .map(|i| {
+                    // at the middle of the segment
+                    let t = (i as f64 + 0.5) * SEGMENT_MS / 1000.0;
+                    let cutoff = CUTOFF_START * f64::powf(CUTOFF_END / CUTOFF_START, t / t60);
+                    Segment {
+                        positive: Vec::new(),
+                        negative: Vec::new(),
+                        lowpass: OnePoleLowpass::new(sample_rate, cutoff.min(0.45 * fs)),
+                        gain: S::from_f64(f64::powf(10.0, -3.0 * t / t60)),
+                    }
+                })
+                .collect();
+
+            // one pulse at a random position in each cell of the grid
+            let mut energy = 0.0;
+            for cell in 0..(length as f64 / grid) as usize {
+                let position = (cell as f64 * grid + rng.next_f64() * grid) as usize;
+                let segment = &mut segments[position / segment_length];
+                if rng.next_f64() < 0.5 {
+                    segment.positive.push(position);
+                } else {
+                    segment.negative.push(position);
+                }
+                energy += segment.gain.to_f64().powi(2);
+            }
+
+            // normalized so that the loudness does not depend on the density and the decay time
+            let scale = S::from_f64(f64::sqrt(0.75 / energy.max(f64::EPSILON)));
+            for segment in &mut segments {
+                segment.gain *= scale;
+            }
+            segments
+        };
+        let segments = [channel(), channel()];
+
+        Self {
+            history: vec![S::ZERO; length.next_power_of_two()],
+            write_ptr: 0,
+            segments,
+        }
+    }
+
+    fn process_mono(&mut self, x: S) -> (S, S) {
+        let mask = self.history.len() - 1;
+        self.write_ptr = (self.write_ptr + 1) & mask;
+        self.history[self.write_ptr] = x;
+
+        let (history, write_ptr) = (&self.history, self.write_ptr);
+        let past = |position: usize| history[write_ptr.wrapping_sub(position) & mask];
+        let [l, r] = &mut self.segments;
+        let output = |segments: &mut [Segment<S>]| {
+            let mut y = S::ZERO;
+            for segment in segments {
+                let mut sum = S::ZERO;
+                for position in &segment.positive {
+                    sum += past(*position);
+                }
+                for position in &segment.negative {
+                    sum -= past(*position);
+                }
+                y += segment.gain * segment.lowpass.process_sample(sum);
+            }
+            y
+        };
+        (output(l), output(r))
+    }
+}
+
+impl<S: Sample> Reverb<S> for VelvetNoise<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        self.process_mono((x.0 + x.1) / S::from_f64(2.0))
+    }
+
+    fn reset(&mut self) {
+        self.history.iter_mut().for_each(|x| *x = S::ZERO);
+        for segment in self.segments.iter_mut().flatten() {
+            segment.lowpass.reset();
+        }
+    }
+}
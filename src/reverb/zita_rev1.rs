@@ -0,0 +1,153 @@
+use super::{Predelay, Reverb};
+use crate::delay::{Delay, DelayLine, Interpolation};
+use crate::filter::{Allpass, Filter};
+use crate::matrix::{FeedbackMatrix, Hadamard};
+use crate::sample::Sample;
+use std::f64::consts::PI;
+
+// https://kokkinizita.linuxaudio.org/linuxaudio/
+// lengths in milliseconds of the diffusion allpasses and of the whole lines including them
+const DIFFUSER_DELAYS: [f64; 8] = [
+    20.346, 24.421, 31.604, 27.333, 22.904, 29.291, 13.458, 19.123,
+];
+const LINE_DELAYS: [f64; 8] = [
+    153.129, 210.389, 127.837, 256.891, 174.713, 192.303, 125.000, 219.991,
+];
+
+// 8-line FDN whose lines decay at different rates below and above the crossover.
+// the left input and output are connected to the even lines and the right ones to the odd lines
+pub struct ZitaRev1<S = f64> {
+    predelay: [Predelay<S>; 2],
+    lines: [Line<S>; 8],
+    matrix: Hadamard<S>,
+}
+
+impl<S: Sample> ZitaRev1<S> {
+    /// `rt_low` and `rt_mid` are the RT60 in seconds below and above `crossover` Hz,
+    /// and the RT60 is halved at `hf_damping` Hz.
+    /// `hf_damping` must be positive, otherwise the damping filters output NaN
+    pub fn new(
+        sample_rate: u32,
+        predelay_ms: f64,
+        crossover: f64,
+        rt_low: f64,
+        rt_mid: f64,
+        hf_damping: f64,
+    ) -> Self {
+        let fs = f64::from(sample_rate);
+        let wlo = 2.0 * PI * crossover / fs;
+        // as in zita-rev1, damping above the Nyquist frequency is limited to it
+        let chi = if hf_damping > 0.49 * fs {
+            2.0
+        } else {
+            1.0 - (2.0 * PI * hf_damping / fs).cos()
+        };
+        let lines = std::array::from_fn(|i| {
+            let (diffuser, line) = (DIFFUSER_DELAYS[i], LINE_DELAYS[i]);
+            let g = if i % 2 == 0 { 0.6 } else { -0.6 };
+            Line {
+                diffuser: Allpass::new(
+                    -g,
+                    -g,
+                    Delay::from_millis(sample_rate, diffuser, Interpolation::Allpass),
+                ),
+                delay: Delay::from_millis(sample_rate, line - diffuser, Interpolation::Allpass),
+                decay: TwoBandDecay::new(line / 1000.0, rt_mid, rt_low, wlo, 0.5 * rt_mid, chi),
+            }
+        });
+        let predelay = || Predelay::new(sample_rate, predelay_ms);
+        Self {
+            predelay: [predelay(), predelay()],
+            lines,
+            matrix: Hadamard::new(8),
+        }
+    }
+}
+
+impl<S: Sample> Reverb<S> for ZitaRev1<S> {
+    fn process_sample(&mut self, x: (S, S)) -> (S, S) {
+        let input_gain = S::from_f64(0.3);
+        let input = [
+            input_gain * self.predelay[0].process_sample(x.0),
+            input_gain * self.predelay[1].process_sample(x.1),
+        ];
+
+        let mut v = [S::ZERO; 8];
+        let mut output = (S::ZERO, S::ZERO);
+        for (i, (v, line)) in v.iter_mut().zip(self.lines.iter_mut()).enumerate() {
+            *v = line.decay.process_sample(line.delay.output());
+            if i % 2 == 0 {
+                output.0 += *v;
+            } else {
+                output.1 += *v;
+            }
+        }
+
+        self.matrix.apply(&mut v);
+
+        for (i, (v, line)) in v.iter().zip(self.lines.iter_mut()).enumerate() {
+            let y = line.diffuser.process_sample(*v + input[i % 2]);
+            line.delay.input(y);
+        }
+
+        let output_gain = S::from_f64(0.5);
+        (output_gain * output.0, output_gain * output.1)
+    }
+
+    fn reset(&mut self) {
+        self.predelay.iter_mut().for_each(Filter::reset);
+        for line in self.lines.iter_mut() {
+            line.diffuser.reset();
+            line.delay.reset();
+            line.decay.reset();
+        }
+    }
+}
+
+struct Line<S> {
+    diffuser: Allpass<S>,
+    delay: Delay<S>,
+    decay: TwoBandDecay<S>,
+}
+
+// gain of a line for the given RT60s, as Filt1 in zita-rev1.
+// the low band is split with a one-pole lowpass, and the high frequencies
+// are damped with another one tuned so that the RT60 is `t_hi` at the damping frequency
+struct TwoBandDecay<S> {
+    gmf: S,
+    glo: S,
+    wlo: S,
+    whi: S,
+    slo: S,
+    shi: S,
+}
+
+impl<S: Sample> TwoBandDecay<S> {
+    fn new(delay: f64, t_mid: f64, t_low: f64, wlo: f64, t_hi: f64, chi: f64) -> Self {
+        let gmf = f64::powf(0.001, delay / t_mid);
+        let glo = f64::powf(0.001, delay / t_low) / gmf - 1.0;
+        let g = f64::powf(0.001, delay / t_hi) / gmf;
+        let t = (1.0 - g * g) / (2.0 * g * g * chi);
+        let whi = ((1.0 + 4.0 * t).sqrt() - 1.0) / (2.0 * t);
+        Self {
+            gmf: S::from_f64(gmf),
+            glo: S::from_f64(glo),
+            wlo: S::from_f64(wlo),
+            whi: S::from_f64(whi),
+            slo: S::ZERO,
+            shi: S::ZERO,
+        }
+    }
+
+    fn process_sample(&mut self, x: S) -> S {
+        self.slo = (self.slo + self.wlo * (x - self.slo)).flush_denormal();
+        let x = x + self.glo * self.slo;
+        self.shi = (self.shi + self.whi * (x - self.shi)).flush_denormal();
+        self.gmf * self.shi
+    }
+
+    fn reset(&mut self) {
+        self.slo = S::ZERO;
+        self.shi = S::ZERO;
+    }
+}
@@ -0,0 +1,62 @@
+use std::fmt::Debug;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+// floating point type that the DSP is performed in
+pub trait Sample:
+    Copy
+    + Default
+    + Debug
+    + PartialEq
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + Sum
+    + Send
+    + Sync
+    + 'static
+{
+    const ZERO: Self;
+
+    fn from_f64(x: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    /// Flushes values small enough to become subnormal to zero.
+    /// Adding and subtracting a tiny constant rounds away anything below its precision.
+    fn flush_denormal(self) -> Self {
+        let offset = Self::from_f64(DENORMAL_OFFSET);
+        self + offset - offset
+    }
+}
+
+const DENORMAL_OFFSET: f64 = 1e-18;
+
+impl Sample for f32 {
+    const ZERO: Self = 0.0;
+
+    fn from_f64(x: f64) -> Self {
+        x as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        f64::from(self)
+    }
+}
+
+impl Sample for f64 {
+    const ZERO: Self = 0.0;
+
+    fn from_f64(x: f64) -> Self {
+        x
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
@@ -0,0 +1,27 @@
+use hound::{SampleFormat, WavReader, WavSpec};
+use std::path::Path;
+
+/// Reads a WAV file as interleaved samples normalized to [-1, 1]
+pub fn read<P: AsRef<Path>>(path: P) -> hound::Result<(WavSpec, Vec<f32>)> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        SampleFormat::Int => match spec.bits_per_sample {
+            16 => reader
+                .samples::<i16>()
+                .collect::<Result<Vec<_>, _>>()?
+                .iter()
+                .map(|s| f32::from(*s) / f32::from(i16::MAX))
+                .collect(),
+            32 => reader
+                .samples::<i32>()
+                .collect::<Result<Vec<_>, _>>()?
+                .iter()
+                .map(|s| *s as f32 / (i32::MAX as f32))
+                .collect(),
+            _ => return Err(hound::Error::Unsupported),
+        },
+        SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+    };
+    Ok((spec, samples))
+}
@@ -0,0 +1,83 @@
+use accent::delay::{Delay, DelayLine, Interpolation, ModulatedDelay, Transition};
+use accent::lfo::{Lfo, Waveform};
+
+fn impulse_response<D: DelayLine>(delay: &mut D, len: usize) -> Vec<f64> {
+    (0..len)
+        .map(|n| {
+            let y = delay.output();
+            delay.input(if n == 0 { 1.0 } else { 0.0 });
+            y
+        })
+        .collect()
+}
+
+#[test]
+fn constant_modulated_delay_matches_thiran_delay() {
+    for order in 1..=4 {
+        let interpolation = Interpolation::Thiran(order);
+        let lfo = Lfo::new(44100, Waveform::Sine, 1.0, 0.0);
+        let mut modulated = ModulatedDelay::new(10.3, 0.0, lfo, interpolation);
+        let mut fixed = Delay::fractional(10.3, interpolation);
+        let expected = impulse_response(&mut fixed, 256);
+        let actual = impulse_response(&mut modulated, 256);
+        for (a, b) in actual.iter().zip(&expected) {
+            assert!(
+                (a - b).abs() < 1e-12,
+                "order {}: {:?} vs {:?}",
+                order,
+                actual,
+                expected
+            );
+        }
+        // allpass, so the energy of the impulse is kept
+        let energy: f64 = actual.iter().map(|y| y * y).sum();
+        assert!(
+            (energy - 1.0).abs() < 1e-6,
+            "order {}: energy {}",
+            order,
+            energy
+        );
+    }
+}
+
+#[test]
+fn glide_keeps_thiran_allpass() {
+    for order in 1..=4 {
+        let mut delay = Delay::with_max_length(10.3, 20.0, Interpolation::Thiran(order));
+        // settles at the target long before the impulse has decayed
+        delay.set_length(10.3, Transition::Glide(64));
+        let glided = impulse_response(&mut delay, 512);
+        let expected = impulse_response(
+            &mut Delay::fractional(10.3, Interpolation::Thiran(order)),
+            512,
+        );
+        for (a, b) in glided.iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-12, "order {}", order);
+        }
+
+        // a low frequency sine is delayed by the gliding length as long as the allpass
+        // keeps its state, which holds signal while the coefficients change
+        let (from, to, start, duration) = (10.3, 14.8, 500usize, 1000);
+        let sine = |n: f64| (2.0 * std::f64::consts::PI * 0.005 * n).sin();
+        let mut delay = Delay::<f64>::with_max_length(from, 20.0, Interpolation::Thiran(order));
+        for n in 0..2000 {
+            if n == start {
+                delay.set_length(to, Transition::Glide(duration));
+            }
+            let progress = n.saturating_sub(start).min(duration) as f64 / duration as f64;
+            let length = from + (to - from) * progress;
+            let error = delay.output() - sine(n as f64 - length);
+            // the first output samples are still filling the line
+            if n > 100 {
+                assert!(
+                    error.abs() < 1e-3,
+                    "order {}: error {} at {}",
+                    order,
+                    error,
+                    n
+                );
+            }
+            delay.input(sine(n as f64));
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use accent::{Freeverb, Reverb, SATREV};
+
+const BLOCK_SIZE: usize = 4096;
+
+// processes silence after a short burst until the tail has died out
+// and checks that no subnormal number is output on the way
+fn assert_no_subnormal_tail<R: Reverb<f32>>(reverb: &mut R, tail_blocks: usize) {
+    let mut input = vec![0.0; BLOCK_SIZE];
+    for (n, x) in input.iter_mut().enumerate().take(256) {
+        *x = (n as f32 * 0.1).sin();
+    }
+    let mut output = (vec![0.0; BLOCK_SIZE], vec![0.0; BLOCK_SIZE]);
+    reverb.process_block((&input, &input), (&mut output.0, &mut output.1));
+    input.iter_mut().for_each(|x| *x = 0.0);
+
+    for _ in 0..tail_blocks {
+        reverb.process_block((&input, &input), (&mut output.0, &mut output.1));
+        assert!(output
+            .0
+            .iter()
+            .chain(output.1.iter())
+            .all(|x| !x.is_subnormal()));
+    }
+}
+
+#[test]
+fn silence_after_input_does_not_output_subnormals() {
+    assert_no_subnormal_tail(&mut Freeverb::new(44100, 0.0, 0.5, 1.0, 1.0, 0.0), 216);
+    assert_no_subnormal_tail(&mut SATREV::new(44100), 216);
+}